@@ -0,0 +1,388 @@
+use crate::computer::{INSTRUCTION_EXIT, INSTRUCTION_JUMP_IF_FALSE, INSTRUCTION_JUMP_IF_TRUE};
+use crate::disassembler::{decode, Instruction, MODE_IMMEDIATE, MODE_POSITION};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    Fallthrough(usize),
+    Jump(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    /// Runs into the next block without branching.
+    Fallthrough,
+    /// Conditional or unconditional jump with an immediate target.
+    Jump,
+    /// Jump whose target is read from memory at runtime.
+    Indirect,
+    Halt,
+    /// The cell does not decode to a valid instruction.
+    Invalid(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+    pub successors: Vec<Edge>,
+}
+
+/// An instruction that writes, in position mode, into a cell that holds code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfModification {
+    pub address: usize,
+    pub target: usize,
+}
+
+#[derive(Debug)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, BasicBlock>,
+    /// Addresses of jumps whose target is not known statically.
+    pub indirect_jumps: Vec<usize>,
+    /// Half-open address ranges never reached from the entry point through
+    /// static edges. Indirect jumps may still land in them at runtime.
+    pub unreachable: Vec<(usize, usize)>,
+    pub self_modifications: Vec<SelfModification>,
+}
+
+/// Builds the control-flow graph of a program by recursive descent from
+/// address 0.
+pub fn build(memory: &[i64]) -> Cfg {
+    let mut instructions: BTreeMap<usize, Option<Instruction>> = BTreeMap::new();
+    let mut leaders = BTreeSet::new();
+    let mut queue = VecDeque::new();
+    leaders.insert(0);
+    queue.push_back(0);
+
+    while let Some(address) = queue.pop_front() {
+        if instructions.contains_key(&address) {
+            continue;
+        }
+        let instruction = decode(memory, address);
+        if let Some(instruction) = &instruction {
+            let (targets, falls_through) = successors(instruction, memory.len());
+            for target in targets {
+                leaders.insert(target);
+                queue.push_back(target);
+            }
+            if falls_through {
+                if is_jump(instruction) {
+                    leaders.insert(instruction.next());
+                }
+                queue.push_back(instruction.next());
+            }
+        }
+        instructions.insert(address, instruction);
+    }
+
+    let mut blocks = BTreeMap::new();
+    let mut indirect_jumps = Vec::new();
+    for leader in &leaders {
+        let block = build_block(*leader, &instructions, &leaders, memory.len());
+        if block.terminator == Terminator::Indirect {
+            indirect_jumps.push(block.instructions.last().unwrap().address);
+        }
+        blocks.insert(*leader, block);
+    }
+
+    let mut code = vec![false; memory.len()];
+    for (address, instruction) in &instructions {
+        match instruction {
+            Some(instruction) => {
+                for cell in &mut code[instruction.address..instruction.next()] {
+                    *cell = true;
+                }
+            }
+            // Execution reaches this cell, so a write may be what turns it
+            // into a valid instruction.
+            None if *address < code.len() => code[*address] = true,
+            None => {}
+        }
+    }
+
+    let mut self_modifications = Vec::new();
+    for instruction in instructions.values().flatten() {
+        if let Some(i) = instruction.output {
+            let target = instruction.parameters[i];
+            if instruction.modes[i] == MODE_POSITION
+                && 0 <= target
+                && (target as usize) < code.len()
+                && code[target as usize]
+            {
+                self_modifications.push(SelfModification {
+                    address: instruction.address,
+                    target: target as usize,
+                });
+            }
+        }
+    }
+
+    let mut unreachable = Vec::new();
+    let mut start = None;
+    for (address, reached) in code.iter().enumerate() {
+        match (reached, start) {
+            (false, None) => start = Some(address),
+            (true, Some(s)) => {
+                unreachable.push((s, address));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        unreachable.push((s, code.len()));
+    }
+
+    return Cfg {
+        blocks: blocks,
+        indirect_jumps: indirect_jumps,
+        unreachable: unreachable,
+        self_modifications: self_modifications,
+    };
+}
+
+fn is_jump(instruction: &Instruction) -> bool {
+    return instruction.opcode == INSTRUCTION_JUMP_IF_TRUE
+        || instruction.opcode == INSTRUCTION_JUMP_IF_FALSE;
+}
+
+/// Static jump targets of an instruction and whether execution can continue
+/// with the next instruction.
+fn successors(instruction: &Instruction, memory_size: usize) -> (Vec<usize>, bool) {
+    if instruction.opcode == INSTRUCTION_EXIT {
+        return (vec![], false);
+    }
+    if !is_jump(instruction) {
+        return (vec![], true);
+    }
+
+    let (condition_mode, target_mode) = (instruction.modes[0], instruction.modes[1]);
+    let (condition, target) = (instruction.parameters[0], instruction.parameters[1]);
+    let mut targets = vec![];
+    if target_mode == MODE_IMMEDIATE && 0 <= target && (target as usize) < memory_size {
+        targets.push(target as usize);
+    }
+    let falls_through = if condition_mode == MODE_IMMEDIATE {
+        // The branch is decided at load time: `jnz 1, x` always jumps.
+        (condition != 0) != (instruction.opcode == INSTRUCTION_JUMP_IF_TRUE)
+    } else {
+        true
+    };
+    if condition_mode == MODE_IMMEDIATE && falls_through {
+        targets.clear();
+    }
+    return (targets, falls_through);
+}
+
+fn build_block(
+    start: usize,
+    instructions: &BTreeMap<usize, Option<Instruction>>,
+    leaders: &BTreeSet<usize>,
+    memory_size: usize,
+) -> BasicBlock {
+    let mut block = BasicBlock {
+        start: start,
+        instructions: vec![],
+        terminator: Terminator::Fallthrough,
+        successors: vec![],
+    };
+
+    let mut address = start;
+    loop {
+        let instruction = match instructions.get(&address) {
+            Some(Some(instruction)) => instruction.clone(),
+            _ => {
+                block.terminator = Terminator::Invalid(address);
+                return block;
+            }
+        };
+        let (targets, falls_through) = successors(&instruction, memory_size);
+        let next = instruction.next();
+        let jump = is_jump(&instruction);
+        // A jump whose immediate condition never fires needs no target.
+        let resolved = instruction.modes[1] == MODE_IMMEDIATE
+            || instruction.modes[0] == MODE_IMMEDIATE && falls_through;
+        let halt = instruction.opcode == INSTRUCTION_EXIT;
+        block.instructions.push(instruction);
+
+        if jump || halt {
+            block.terminator = if halt {
+                Terminator::Halt
+            } else if resolved {
+                Terminator::Jump
+            } else {
+                Terminator::Indirect
+            };
+            block.successors = targets.into_iter().map(Edge::Jump).collect();
+            if falls_through {
+                block.successors.push(Edge::Fallthrough(next));
+            }
+            return block;
+        }
+        if leaders.contains(&next) {
+            block.successors.push(Edge::Fallthrough(next));
+            return block;
+        }
+        address = next;
+    }
+}
+
+impl Cfg {
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph cfg {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for instruction in &block.instructions {
+                write!(label, "{}: {}\\l", instruction.address, instruction).unwrap();
+            }
+            let style = match block.terminator {
+                Terminator::Indirect => ", color=orange",
+                Terminator::Invalid(_) => ", color=red",
+                _ => "",
+            };
+            if let Terminator::Invalid(address) = block.terminator {
+                write!(label, "{}: <invalid>\\l", address).unwrap();
+            }
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, style).unwrap();
+        }
+
+        for block in self.blocks.values() {
+            for edge in &block.successors {
+                match edge {
+                    Edge::Fallthrough(to) => writeln!(dot, "    b{} -> b{};", block.start, to),
+                    Edge::Jump(to) => {
+                        writeln!(dot, "    b{} -> b{} [label=\"jump\"];", block.start, to)
+                    }
+                }
+                .unwrap();
+            }
+        }
+
+        for (start, end) in &self.unreachable {
+            writeln!(
+                dot,
+                "    u{} [label=\"unreachable {}..{}\", style=dashed, color=gray];",
+                start, start, end
+            )
+            .unwrap();
+        }
+
+        for modification in &self.self_modifications {
+            writeln!(
+                dot,
+                "    // self-modifying write at {} into code at {}",
+                modification.address, modification.target
+            )
+            .unwrap();
+        }
+
+        writeln!(dot, "}}").unwrap();
+        return dot;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::load_program;
+
+    #[test]
+    fn test_straight_line() {
+        let cfg = build(&[1, 0, 0, 0, 99]);
+        assert_eq!(cfg.blocks.len(), 1);
+        let block = &cfg.blocks[&0];
+        assert_eq!(block.instructions.len(), 2);
+        assert_eq!(block.terminator, Terminator::Halt);
+        assert!(block.successors.is_empty());
+        assert_eq!(cfg.unreachable, vec![]);
+        // `add [0], [0], [0]` overwrites its own opcode.
+        assert_eq!(
+            cfg.self_modifications,
+            vec![SelfModification {
+                address: 0,
+                target: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_conditional_jump() {
+        // 0: jz [9], 7
+        // 3: out 1
+        // 5: hlt
+        // 6: <data>
+        // 7: out 2
+        // 9: hlt
+        let cfg = build(&[1006, 9, 7, 104, 1, 99, 0, 104, 2, 99]);
+        assert_eq!(
+            cfg.blocks.keys().cloned().collect::<Vec<_>>(),
+            vec![0, 3, 7]
+        );
+        assert_eq!(
+            cfg.blocks[&0].successors,
+            vec![Edge::Jump(7), Edge::Fallthrough(3)]
+        );
+        assert_eq!(cfg.unreachable, vec![(6, 7)]);
+        assert!(cfg.indirect_jumps.is_empty());
+    }
+
+    #[test]
+    fn test_unconditional_jump() {
+        // 0: jnz 1, 5
+        // 3: <unreachable out 1>
+        // 5: hlt
+        let cfg = build(&[1105, 1, 5, 104, 1, 99]);
+        assert_eq!(cfg.blocks[&0].successors, vec![Edge::Jump(5)]);
+        assert_eq!(cfg.unreachable, vec![(3, 5)]);
+    }
+
+    #[test]
+    fn test_indirect_jump() {
+        let cfg = build(&[5, 4, 5, 99, 1, 3]);
+        assert_eq!(cfg.indirect_jumps, vec![0]);
+        assert_eq!(cfg.blocks[&0].terminator, Terminator::Indirect);
+        assert_eq!(cfg.blocks[&0].successors, vec![Edge::Fallthrough(3)]);
+    }
+
+    #[test]
+    fn test_invalid_instruction() {
+        let cfg = build(&[104, 1, 42]);
+        assert_eq!(cfg.blocks[&0].terminator, Terminator::Invalid(2));
+    }
+
+    #[test]
+    fn test_patched_opcode() {
+        // `in [0]; add [0], [6], [6]` turns the invalid 1100 into a valid add.
+        let cfg = build(&[3, 0, 1, 0, 6, 6, 1100, 1, 1, 0, 99]);
+        assert_eq!(cfg.blocks[&0].terminator, Terminator::Invalid(6));
+        assert_eq!(
+            cfg.self_modifications,
+            vec![
+                SelfModification {
+                    address: 0,
+                    target: 0
+                },
+                SelfModification {
+                    address: 2,
+                    target: 6
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_boost_dot() {
+        let cfg = build(&load_program("inputs/day_9.txt"));
+        assert!(!cfg.indirect_jumps.is_empty());
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph cfg {"));
+        assert!(dot.contains("b0 [label=\"0: "));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+}
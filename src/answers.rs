@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Known answers by day and part.
+pub type Answers = BTreeMap<(u8, u8), String>;
+
+/// Reads answers from the small subset of TOML that `answers.toml` uses: a
+/// table per day holding `part_1` and `part_2` strings, which can be
+/// multi-line strings whose closing quotes are on a line of their own.
+///
+/// ```toml
+/// [8]
+/// part_1 = "1596"
+/// part_2 = """
+///  #  #
+/// """
+/// ```
+pub fn parse(text: &str) -> Result<Answers, String> {
+    let mut answers = BTreeMap::new();
+    let mut day = None;
+    let mut lines = text.lines().enumerate();
+    while let Some((n, line)) = lines.next() {
+        let error = |message: &str| format!("Line {}: {}: {}", n + 1, message, line.trim());
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            match line[1..line.len() - 1].trim().parse::<u8>() {
+                Ok(d) => day = Some(d),
+                Err(_) => return Err(error("invalid day")),
+            }
+            continue;
+        }
+
+        let (key, value) = match line.find('=') {
+            Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+            None => return Err(error("expected key = value")),
+        };
+        let part = match key {
+            "part_1" => 1,
+            "part_2" => 2,
+            _ => return Err(error("unknown key")),
+        };
+        let day = day.ok_or_else(|| error("answer outside of a day"))?;
+        let answer = if value == "\"\"\"" {
+            let mut answer = String::new();
+            loop {
+                match lines.next() {
+                    Some((_, l)) if l.trim() == "\"\"\"" => break,
+                    Some((_, l)) => {
+                        answer += l;
+                        answer.push('\n');
+                    }
+                    None => return Err(error("unterminated string")),
+                }
+            }
+            answer
+        } else if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            String::from(&value[1..value.len() - 1])
+        } else {
+            return Err(error("expected a string"));
+        };
+        if answers.insert((day, part), answer).is_some() {
+            return Err(error("duplicate answer"));
+        }
+    }
+    return Ok(answers);
+}
+
+pub fn load(file_name: &str) -> Result<Answers, String> {
+    let text = fs::read_to_string(file_name).map_err(|e| format!("{}: {}", file_name, e))?;
+    return parse(&text).map_err(|e| format!("{}: {}", file_name, e));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let answers =
+            parse("# Comment\n[1]\npart_1 = \"12\"\n\n[8]\npart_2 = \"\"\"\n #\n# \n\"\"\"\n")
+                .unwrap();
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers[&(1, 1)], "12");
+        assert_eq!(answers[&(8, 2)], " #\n# \n");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse("part_1 = \"1\""),
+            Err(String::from(
+                "Line 1: answer outside of a day: part_1 = \"1\""
+            ))
+        );
+        assert_eq!(
+            parse("[1]\npart_3 = \"1\""),
+            Err(String::from("Line 2: unknown key: part_3 = \"1\""))
+        );
+        assert_eq!(
+            parse("[1]\npart_1 = 1"),
+            Err(String::from("Line 2: expected a string: part_1 = 1"))
+        );
+        assert_eq!(
+            parse("[1]\npart_2 = \"\"\"\n#"),
+            Err(String::from("Line 2: unterminated string: part_2 = \"\"\""))
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use crate::computer::{Computer, Fault, IntComputer, Outcome, INSTRUCTION_READ};
+use crate::reader;
+use crate::writer;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+
+/// Instructions a machine runs before letting other tasks make progress.
+const YIELD_INTERVAL: u64 = 1024;
+
+/// Input of an async machine. `None` means no more values will come.
+pub trait Source {
+    fn poll_read(&mut self, cx: &mut Context) -> Poll<Option<i64>>;
+}
+
+/// Output of an async machine.
+pub trait Sink {
+    fn poll_write(&mut self, cx: &mut Context, value: i64) -> Poll<()>;
+}
+
+struct Queue {
+    values: VecDeque<i64>,
+    waker: Option<Waker>,
+    senders: usize,
+    closed: bool,
+}
+
+/// Sending end of an unbounded single-threaded channel. Values sent after the
+/// receiver is dropped are discarded.
+pub struct Sender {
+    queue: Rc<RefCell<Queue>>,
+}
+
+pub struct Receiver {
+    queue: Rc<RefCell<Queue>>,
+}
+
+pub fn channel() -> (Sender, Receiver) {
+    let queue = Rc::new(RefCell::new(Queue {
+        values: VecDeque::new(),
+        waker: None,
+        senders: 1,
+        closed: false,
+    }));
+    return (
+        Sender {
+            queue: queue.clone(),
+        },
+        Receiver { queue: queue },
+    );
+}
+
+impl Sender {
+    pub fn send(&self, value: i64) {
+        let mut queue = self.queue.borrow_mut();
+        if queue.closed {
+            return;
+        }
+        queue.values.push_back(value);
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Clone for Sender {
+    fn clone(&self) -> Sender {
+        self.queue.borrow_mut().senders += 1;
+        return Sender {
+            queue: self.queue.clone(),
+        };
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let mut queue = self.queue.borrow_mut();
+        queue.senders -= 1;
+        if queue.senders == 0 {
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Sink for Sender {
+    fn poll_write(&mut self, _: &mut Context, value: i64) -> Poll<()> {
+        self.send(value);
+        return Poll::Ready(());
+    }
+}
+
+impl Receiver {
+    /// Values received so far that nobody read.
+    pub fn drain(&self) -> Vec<i64> {
+        return self.queue.borrow_mut().values.drain(..).collect();
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        let mut queue = self.queue.borrow_mut();
+        queue.closed = true;
+        queue.values.clear();
+    }
+}
+
+impl Source for Receiver {
+    fn poll_read(&mut self, cx: &mut Context) -> Poll<Option<i64>> {
+        let mut queue = self.queue.borrow_mut();
+        if let Some(value) = queue.values.pop_front() {
+            return Poll::Ready(Some(value));
+        }
+        if queue.senders == 0 {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        return Poll::Pending;
+    }
+}
+
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        return Poll::Pending;
+    }
+}
+
+/// Runs the machine as a future until it halts or faults. Reads await the
+/// source, which replaces the machine's reader, and every output is sent to
+/// each sink in turn as well as to the machine's own writers. A read after the
+/// source closed is a `NoReader` fault. The machine is handed back with the
+/// outcome and with its own reader and writers.
+pub async fn execute(
+    mut computer: IntComputer,
+    mut source: Box<dyn Source>,
+    mut sinks: Vec<Box<dyn Sink>>,
+) -> (IntComputer, Outcome) {
+    let slot = Rc::new(Cell::new(None));
+    let outputs = Rc::new(RefCell::new(Vec::new()));
+    let reader = computer.take_reader();
+    computer.set_reader(Box::new(reader::slot(slot.clone())));
+    computer.register_writer(Box::new(writer::buffer(outputs.clone())));
+
+    let mut executed: u64 = 0;
+    let outcome = loop {
+        if computer.next_opcode() == Some(INSTRUCTION_READ) {
+            match poll_fn(|cx| source.poll_read(cx)).await {
+                Some(value) => slot.set(Some(value)),
+                None => break Outcome::Fault(Fault::NoReader(computer.get_pointer())),
+            }
+        }
+        let halted = match computer.step() {
+            Ok(halted) => halted,
+            Err(fault) => break Outcome::Fault(fault),
+        };
+        let values: Vec<i64> = outputs.borrow_mut().drain(..).collect();
+        for value in values {
+            for sink in &mut sinks {
+                poll_fn(|cx| sink.poll_write(cx, value)).await;
+            }
+        }
+        if halted {
+            break Outcome::Halted;
+        }
+        executed += 1;
+        if executed % YIELD_INTERVAL == 0 {
+            YieldNow { yielded: false }.await;
+        }
+    };
+
+    // The buffer was registered last.
+    let mut writers = computer.take_writers();
+    writers.pop();
+    for writer in writers {
+        computer.register_writer(writer);
+    }
+    computer.take_reader();
+    if let Some(reader) = reader {
+        computer.set_reader(reader);
+    }
+    return (computer, outcome);
+}
+
+/// Result of a spawned task, available once the executor completed it.
+pub struct Handle<T> {
+    result: Rc<RefCell<Option<T>>>,
+}
+
+impl<T> Handle<T> {
+    pub fn take(&self) -> Option<T> {
+        return self.result.borrow_mut().take();
+    }
+}
+
+struct Notify {
+    task: usize,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl Wake for Notify {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.ready.lock().unwrap().push_back(self.task);
+    }
+}
+
+/// Polls tasks on the current thread, each one only after it was woken.
+pub struct Executor {
+    tasks: Vec<Option<Pin<Box<dyn Future<Output = ()>>>>>,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+pub fn executor() -> Executor {
+    return Executor {
+        tasks: Vec::new(),
+        ready: Arc::new(Mutex::new(VecDeque::new())),
+    };
+}
+
+impl Executor {
+    pub fn spawn<T: 'static>(&mut self, future: impl Future<Output = T> + 'static) -> Handle<T> {
+        let result = Rc::new(RefCell::new(None));
+        let slot = result.clone();
+        self.tasks.push(Some(Box::pin(async move {
+            let value = future.await;
+            *slot.borrow_mut() = Some(value);
+        })));
+        self.ready.lock().unwrap().push_back(self.tasks.len() - 1);
+        return Handle { result: result };
+    }
+
+    /// Runs until no task can make progress and returns how many tasks are
+    /// still waiting, which is zero unless some of them deadlocked.
+    pub fn run(&mut self) -> usize {
+        loop {
+            let task = match self.ready.lock().unwrap().pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+            let future = match &mut self.tasks[task] {
+                Some(future) => future,
+                None => continue,
+            };
+            let waker = Waker::from(Arc::new(Notify {
+                task: task,
+                ready: self.ready.clone(),
+            }));
+            let mut cx = Context::from_waker(&waker);
+            if future.as_mut().poll(&mut cx).is_ready() {
+                self.tasks[task] = None;
+            }
+        }
+        return self.tasks.iter().filter(|t| t.is_some()).count();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer;
+    use crate::computer::{Level, Limits};
+
+    /// Reads a number, adds one and writes it, for as long as inputs come.
+    const INCREMENT: [i64; 11] = [3, 11, 1001, 11, 1, 11, 4, 11, 1105, 1, 0];
+
+    fn machine(
+        executor: &mut Executor,
+        program: &[i64],
+        input: Receiver,
+        outputs: Vec<Sender>,
+    ) -> Handle<(IntComputer, Outcome)> {
+        let computer = computer::new(Level::Day5, program.len() + 1, program);
+        let sinks = outputs
+            .into_iter()
+            .map(|s| Box::new(s) as Box<dyn Sink>)
+            .collect();
+        return executor.spawn(execute(computer, Box::new(input), sinks));
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut executor = executor();
+        let (first, mut input) = channel();
+        let mut handles = vec![];
+        for _ in 0..200 {
+            let (tx, rx) = channel();
+            handles.push(machine(&mut executor, &INCREMENT, input, vec![tx]));
+            input = rx;
+        }
+        first.send(0);
+        first.send(100);
+        drop(first);
+        assert_eq!(executor.run(), 0);
+        assert_eq!(input.drain(), vec![200, 300]);
+        for handle in handles {
+            let (computer, outcome) = handle.take().unwrap();
+            assert_eq!(outcome, Outcome::Fault(Fault::NoReader(0)));
+            assert_eq!(computer.get_instruction_count(), 8);
+        }
+    }
+
+    #[test]
+    fn test_deadlock() {
+        let mut executor = executor();
+        let (tx, rx) = channel();
+        let handle = machine(&mut executor, &INCREMENT, rx, vec![]);
+        assert_eq!(executor.run(), 1);
+        assert!(handle.take().is_none());
+        drop(tx);
+    }
+
+    #[test]
+    fn test_feedback_loop() {
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let mut executor = executor();
+        let channels: Vec<(Sender, Receiver)> = (0..5).map(|_| channel()).collect();
+        let (senders, receivers): (Vec<Sender>, Vec<Receiver>) = channels.into_iter().unzip();
+        for (sender, phase) in senders.iter().zip(&[9, 8, 7, 6, 5]) {
+            sender.send(*phase);
+        }
+        senders[0].send(0);
+        let (answer, answers) = channel();
+        for (i, receiver) in receivers.into_iter().enumerate() {
+            let mut outputs = vec![senders[(i + 1) % 5].clone()];
+            if i == 4 {
+                outputs.push(answer.clone());
+            }
+            machine(&mut executor, &program, receiver, outputs);
+        }
+        drop(senders);
+        assert_eq!(executor.run(), 0);
+        assert_eq!(answers.drain().pop(), Some(139629729));
+    }
+
+    #[test]
+    fn test_own_reader_and_writers() {
+        let mut computer = computer::new(Level::Day5, INCREMENT.len() + 1, &INCREMENT);
+        let initial = computer.snapshot();
+        let value = Rc::new(Cell::new(Some(41)));
+        let log = Rc::new(RefCell::new(Vec::new()));
+        computer.set_reader(Box::new(reader::slot(value.clone())));
+        computer.register_writer(Box::new(writer::buffer(log.clone())));
+
+        let mut executor = executor();
+        let (tx, rx) = channel();
+        tx.send(1);
+        drop(tx);
+        let handle = executor.spawn(execute(computer, Box::new(rx), vec![]));
+        assert_eq!(executor.run(), 0);
+        let (mut computer, _) = handle.take().unwrap();
+        assert_eq!(*log.borrow(), [2]);
+
+        // Run again, the machine reads from its own reader and writes to its
+        // own writer only.
+        computer.restore(&initial);
+        let limits = Limits {
+            max_instructions: Some(4),
+            deadline: None,
+            cancel: None,
+        };
+        computer.execute_with_limits(&limits);
+        assert_eq!(value.get(), None);
+        assert_eq!(*log.borrow(), [2, 42]);
+    }
+
+    struct Noop;
+
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[test]
+    fn test_endless_loop_yields() {
+        let computer = computer::new(Level::Day5, 3, &[1105, 1, 0]);
+        let (_tx, rx) = channel();
+        let mut future = Box::pin(execute(computer, Box::new(rx), vec![]));
+        let waker = Waker::from(Arc::new(Noop));
+        let mut cx = Context::from_waker(&waker);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+    }
+}
@@ -1,172 +1,499 @@
+use crate::device::Device;
+use crate::dialect;
+use crate::dialect::{Dialect, Opcode, Parameter};
+use crate::parse;
 use crate::reader::Reader;
+use crate::tracer::Tracer;
 use crate::writer::Writer;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
-const INSTRUCTION_ADD: i64 = 1;
-const INSTRUCTION_MUL: i64 = 2;
-const INSTRUCTION_READ: i64 = 3;
-const INSTRUCTION_WRITE: i64 = 4;
-const INSTRUCTION_JUMP_IF_TRUE: i64 = 5;
-const INSTRUCTION_JUMP_IF_FALSE: i64 = 6;
-const INSTRUCTION_LESS_THAN: i64 = 7;
-const INSTRUCTION_EQUALS: i64 = 8;
-const INSTRUCTION_RELATIVE_ADD: i64 = 9;
-const INSTRUCTION_EXIT: i64 = 99;
+pub const INSTRUCTION_ADD: i64 = 1;
+pub const INSTRUCTION_MUL: i64 = 2;
+pub const INSTRUCTION_READ: i64 = 3;
+pub const INSTRUCTION_WRITE: i64 = 4;
+pub const INSTRUCTION_JUMP_IF_TRUE: i64 = 5;
+pub const INSTRUCTION_JUMP_IF_FALSE: i64 = 6;
+pub const INSTRUCTION_LESS_THAN: i64 = 7;
+pub const INSTRUCTION_EQUALS: i64 = 8;
+pub const INSTRUCTION_RELATIVE_ADD: i64 = 9;
+pub const INSTRUCTION_EXIT: i64 = 99;
+
+/// Instruction set as of the puzzle that introduced it. Each level extends
+/// the one before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Add, multiply and halt, with position mode only.
+    Day2,
+    /// Input, output, jumps, comparisons and immediate mode.
+    Day5,
+    /// Relative base adjustment and relative mode.
+    Day9,
+}
+
+impl Level {
+    pub fn has_opcode(self, opcode: i64) -> bool {
+        let introduced = match opcode {
+            INSTRUCTION_ADD | INSTRUCTION_MUL | INSTRUCTION_EXIT => Level::Day2,
+            INSTRUCTION_RELATIVE_ADD => Level::Day9,
+            _ if parameter_count(opcode).is_some() => Level::Day5,
+            _ => return false,
+        };
+        return introduced <= self;
+    }
+
+    pub fn has_mode(self, mode: i64) -> bool {
+        let introduced = match mode {
+            0 => Level::Day2,
+            1 => Level::Day5,
+            2 => Level::Day9,
+            _ => return false,
+        };
+        return introduced <= self;
+    }
+}
 
 pub trait Computer {
     fn execute(&mut self) -> i8;
+    /// Runs like `execute` but stops before the next instruction once a limit
+    /// is reached. The machine is left as it was, so calling again resumes.
+    /// A read blocked on its reader cannot be interrupted.
+    fn execute_with_limits(&mut self, limits: &Limits) -> Outcome;
+    /// Why the last `execute` returned -1.
+    fn get_fault(&self) -> Option<Fault>;
     fn get_memory_at(&self, position: usize) -> i64;
+    fn get_memory(&self) -> &[i64];
+    fn get_instruction_count(&self) -> u64;
     fn register_writer(&mut self, writer: Box<dyn Writer>);
+    fn clear_writers(&mut self);
+    /// Removes the writers, in the order they were registered.
+    fn take_writers(&mut self) -> Vec<Box<dyn Writer>>;
     fn set_reader(&mut self, reader: Box<dyn Reader>);
+    fn take_reader(&mut self) -> Option<Box<dyn Reader>>;
+    fn set_tracer(&mut self, tracer: Box<dyn Tracer>);
+    /// Adds a custom opcode. The handler gets the values of the read
+    /// parameters in order and returns the value for the write parameter,
+    /// which is ignored when the opcode has none.
+    fn register_opcode(&mut self, opcode: Opcode, handler: Handler);
+    /// Maps a device over the memory starting at `start`. The range must fit
+    /// in memory and not overlap another device.
+    fn map_device(&mut self, start: usize, device: Box<dyn Device>);
+    fn snapshot(&self) -> Snapshot;
+    fn restore(&mut self, snapshot: &Snapshot);
+}
+
+/// Host code run by a custom opcode.
+pub type Handler = Box<dyn FnMut(&[i64]) -> i64>;
+
+/// An instruction the machine cannot execute. Each variant holds the address
+/// of the faulting instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fault {
+    InvalidOpcode(usize),
+    InvalidMode(usize),
+    ImmediateWrite(usize),
+    /// An address, negative or past the end of memory, that the instruction
+    /// reads, writes, jumps to or occupies.
+    InvalidAddress(usize, i64),
+    Overflow(usize),
+    /// A read without a reader, or after the input of an async run closed.
+    NoReader(usize),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::InvalidOpcode(p) => write!(f, "invalid opcode at {}", p),
+            Fault::InvalidMode(p) => write!(f, "invalid parameter mode at {}", p),
+            Fault::ImmediateWrite(p) => write!(f, "write in immediate mode at {}", p),
+            Fault::InvalidAddress(p, a) => write!(f, "invalid address {} used at {}", a, p),
+            Fault::Overflow(p) => write!(f, "overflow at {}", p),
+            Fault::NoReader(p) => write!(f, "read without a reader at {}", p),
+        }
+    }
+}
+
+/// How often the deadline is compared against the clock.
+const DEADLINE_INTERVAL: u64 = 1024;
+
+/// Bounds for `execute_with_limits`. Unset fields do not limit the run.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// Instructions executed by this call, not counting earlier ones.
+    pub max_instructions: Option<u64>,
+    pub deadline: Option<Instant>,
+    /// Stops the run once another thread sets it to true.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Instructions,
+    Deadline,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Halted,
+    Fault(Fault),
+    LimitExceeded(Limit),
+    /// The host stopped the run before a read, having no input to give yet.
+    Paused,
+}
+
+/// Machine state without the attached reader, writers and tracer, with the
+/// state of every mapped device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    memory: Vec<i64>,
+    pointer: usize,
+    relative_base: i64,
+    instruction_count: u64,
+    devices: Vec<Vec<i64>>,
 }
 
 pub struct IntComputer {
     memory: Vec<i64>,
     pointer: usize,
     relative_base: i64,
+    instruction_count: u64,
     reader: Option<Box<dyn Reader>>,
     writers: Vec<Box<dyn Writer>>,
+    tracer: Option<Box<dyn Tracer>>,
+    fault: Option<Fault>,
+    dialect: Dialect,
+    handlers: BTreeMap<i64, Handler>,
+    /// Devices with the address and size they are mapped at.
+    devices: Vec<(usize, usize, Box<dyn Device>)>,
 }
 
-pub fn new(memory_size: usize, program: &Vec<i64>) -> IntComputer {
+/// A machine that only understands the instructions and modes of `level`.
+pub fn new(level: Level, memory_size: usize, program: &[i64]) -> IntComputer {
     assert!(program.len() <= memory_size);
     let mut memory = vec![0; memory_size];
-    for i in 0..program.len() {
-        memory[i] = program[i];
-    }
+    memory[..program.len()].copy_from_slice(program);
 
     return IntComputer {
         memory: memory,
         pointer: 0,
         relative_base: 0,
+        instruction_count: 0,
         reader: Option::None,
         writers: Vec::new(),
+        tracer: Option::None,
+        fault: Option::None,
+        dialect: dialect::new(level),
+        handlers: BTreeMap::new(),
+        devices: Vec::new(),
     };
 }
 
+pub fn load_program(file_name: &str) -> Vec<i64> {
+    let content = fs::read_to_string(file_name).expect("Could not read file");
+    return parse::program(&content).unwrap_or_else(|e| panic!("{}: {}", file_name, e));
+}
+
 impl IntComputer {
-    fn get_value(&self, mode: i64, parameter: i64) -> i64 {
+    fn address(&self, address: i64) -> Result<usize, Fault> {
+        if address < 0 || address as usize >= self.memory.len() {
+            return Err(Fault::InvalidAddress(self.pointer, address));
+        }
+        return Ok(address as usize);
+    }
+
+    fn relative_address(&self, parameter: i64) -> Result<usize, Fault> {
+        match self.relative_base.checked_add(parameter) {
+            Some(address) => return self.address(address),
+            None => return Err(Fault::Overflow(self.pointer)),
+        }
+    }
+
+    /// Memory cells a device is mapped over hold the value last read from or
+    /// written to it there.
+    fn load(&mut self, address: usize) -> i64 {
+        for (start, size, device) in &mut self.devices {
+            if *start <= address && address < *start + *size {
+                self.memory[address] = device.read(address - *start);
+            }
+        }
+        return self.memory[address];
+    }
+
+    fn store(&mut self, address: usize, v: i64) {
+        for (start, size, device) in &mut self.devices {
+            if *start <= address && address < *start + *size {
+                device.write(address - *start, v);
+            }
+        }
+        self.memory[address] = v;
+    }
+
+    fn get_value(&mut self, mode: i64, parameter: i64) -> Result<i64, Fault> {
+        if !self.dialect.has_mode(mode) {
+            return Err(Fault::InvalidMode(self.pointer));
+        }
         let v: i64;
         if mode == 1 {
             v = parameter;
         } else if mode == 2 {
-            v = self.memory[(self.relative_base + parameter) as usize]
+            let address = self.relative_address(parameter)?;
+            v = self.load(address);
+        } else if mode == 0 {
+            let address = self.address(parameter)?;
+            v = self.load(address);
         } else {
-            v = self.memory[parameter as usize];
+            return Err(Fault::InvalidMode(self.pointer));
         }
-        return v;
+        return Ok(v);
     }
 
-    fn set_value(&mut self, mode: i64, p: usize, v: i64) {
+    fn write_address(&self, mode: i64, p: i64) -> Result<usize, Fault> {
+        if !self.dialect.has_mode(mode) {
+            return Err(Fault::InvalidMode(self.pointer));
+        }
         match mode {
-            0 => self.memory[p] = v,
-            1 => panic!("Cannot set value in immediate mode"),
-            2 => self.memory[(p as i64 + self.relative_base) as usize] = v,
-            _ => panic!("Mode not supported"),
+            0 => return self.address(p),
+            1 => return Err(Fault::ImmediateWrite(self.pointer)),
+            2 => return self.relative_address(p),
+            _ => return Err(Fault::InvalidMode(self.pointer)),
         }
     }
 
-    fn add(&mut self, am: i64, bm: i64, cm: i64) {
-        let a = self.get_value(am, self.memory[self.pointer + 1]);
-        let b = self.get_value(bm, self.memory[self.pointer + 2]);
-        let r = self.memory[self.pointer + 3] as usize;
-        self.set_value(cm, r, a + b);
+    fn set_value(&mut self, mode: i64, p: i64, v: i64) -> Result<(), Fault> {
+        let address = self.write_address(mode, p)?;
+        self.store(address, v);
+        return Ok(());
+    }
+
+    fn add(&mut self, am: i64, bm: i64, cm: i64) -> Result<(), Fault> {
+        let a = self.get_value(am, self.memory[self.pointer + 1])?;
+        let b = self.get_value(bm, self.memory[self.pointer + 2])?;
+        let r = self.memory[self.pointer + 3];
+        let v = a.checked_add(b).ok_or(Fault::Overflow(self.pointer))?;
+        self.set_value(cm, r, v)?;
         self.pointer += 4;
+        return Ok(());
     }
 
-    fn mul(&mut self, am: i64, bm: i64, cm: i64) {
-        let a = self.get_value(am, self.memory[self.pointer + 1]);
-        let b = self.get_value(bm, self.memory[self.pointer + 2]);
-        let r = self.memory[self.pointer + 3] as usize;
-        self.set_value(cm, r, a * b);
+    fn mul(&mut self, am: i64, bm: i64, cm: i64) -> Result<(), Fault> {
+        let a = self.get_value(am, self.memory[self.pointer + 1])?;
+        let b = self.get_value(bm, self.memory[self.pointer + 2])?;
+        let r = self.memory[self.pointer + 3];
+        let v = a.checked_mul(b).ok_or(Fault::Overflow(self.pointer))?;
+        self.set_value(cm, r, v)?;
         self.pointer += 4;
+        return Ok(());
     }
 
-    fn read(&mut self, am: i64) {
-        let r = self.memory[self.pointer + 1] as usize;
+    fn read(&mut self, am: i64) -> Result<(), Fault> {
+        // The address is checked first so a faulting read loses no input.
+        let address = self.write_address(am, self.memory[self.pointer + 1])?;
         let v = if let Some(reader) = &mut self.reader {
             reader.as_mut().read()
         } else {
-            panic!("Cannot read, no reader defined")
+            return Err(Fault::NoReader(self.pointer));
         };
-        self.set_value(am, r, v);
+        self.store(address, v);
         self.pointer += 2;
+        return Ok(());
     }
 
-    fn write(&mut self, am: i64) {
-        let a = self.get_value(am, self.memory[self.pointer + 1]);
+    fn write(&mut self, am: i64) -> Result<(), Fault> {
+        let a = self.get_value(am, self.memory[self.pointer + 1])?;
         for writer in &self.writers {
             writer.as_ref().write(a);
         }
         self.pointer += 2;
+        return Ok(());
     }
 
-    fn jump_if_true(&mut self, am: i64, bm: i64) {
-        let a = self.get_value(am, self.memory[self.pointer + 1]);
-        let b = self.get_value(bm, self.memory[self.pointer + 2]) as usize;
-        if a != 0 {
-            self.pointer = b;
+    fn jump(&mut self, condition: bool, bm: i64) -> Result<(), Fault> {
+        let b = self.get_value(bm, self.memory[self.pointer + 2])?;
+        if condition {
+            // Only the start of the target is checked here, the next fetch
+            // checks that the whole instruction fits.
+            self.pointer = self.address(b)?;
         } else {
             self.pointer += 3;
         }
+        return Ok(());
     }
 
-    fn jump_if_false(&mut self, am: i64, bm: i64) {
-        let a = self.get_value(am, self.memory[self.pointer + 1]);
-        let b = self.get_value(bm, self.memory[self.pointer + 2]) as usize;
-        if a == 0 {
-            self.pointer = b;
-        } else {
-            self.pointer += 3;
-        }
+    fn jump_if_true(&mut self, am: i64, bm: i64) -> Result<(), Fault> {
+        let a = self.get_value(am, self.memory[self.pointer + 1])?;
+        return self.jump(a != 0, bm);
+    }
+
+    fn jump_if_false(&mut self, am: i64, bm: i64) -> Result<(), Fault> {
+        let a = self.get_value(am, self.memory[self.pointer + 1])?;
+        return self.jump(a == 0, bm);
     }
 
-    fn less_than(&mut self, am: i64, bm: i64, cm: i64) {
-        let a = self.get_value(am, self.memory[self.pointer + 1]);
-        let b = self.get_value(bm, self.memory[self.pointer + 2]);
-        let r = self.memory[self.pointer + 3] as usize;
-        self.set_value(cm, r, if a < b { 1 } else { 0 });
+    fn less_than(&mut self, am: i64, bm: i64, cm: i64) -> Result<(), Fault> {
+        let a = self.get_value(am, self.memory[self.pointer + 1])?;
+        let b = self.get_value(bm, self.memory[self.pointer + 2])?;
+        let r = self.memory[self.pointer + 3];
+        self.set_value(cm, r, if a < b { 1 } else { 0 })?;
         self.pointer += 4;
+        return Ok(());
     }
 
-    fn equals(&mut self, am: i64, bm: i64, cm: i64) {
-        let a = self.get_value(am, self.memory[self.pointer + 1]);
-        let b = self.get_value(bm, self.memory[self.pointer + 2]);
-        let r = self.memory[self.pointer + 3] as usize;
-        self.set_value(cm, r, if a == b { 1 } else { 0 });
+    fn equals(&mut self, am: i64, bm: i64, cm: i64) -> Result<(), Fault> {
+        let a = self.get_value(am, self.memory[self.pointer + 1])?;
+        let b = self.get_value(bm, self.memory[self.pointer + 2])?;
+        let r = self.memory[self.pointer + 3];
+        self.set_value(cm, r, if a == b { 1 } else { 0 })?;
         self.pointer += 4;
+        return Ok(());
     }
 
-    fn relative_add(&mut self, am: i64) {
-        let a = self.get_value(am, self.memory[self.pointer + 1]);
-        self.relative_base += a;
+    fn relative_add(&mut self, am: i64) -> Result<(), Fault> {
+        let a = self.get_value(am, self.memory[self.pointer + 1])?;
+        self.relative_base = self
+            .relative_base
+            .checked_add(a)
+            .ok_or(Fault::Overflow(self.pointer))?;
         self.pointer += 2;
+        return Ok(());
+    }
+
+    fn custom(&mut self, opcode: i64, modes: [i64; 3]) -> Result<(), Fault> {
+        let parameters = self.dialect.get_custom(opcode).unwrap().parameters.clone();
+        let mut values = Vec::new();
+        let mut output = None;
+        for (i, parameter) in parameters.iter().enumerate() {
+            let p = self.memory[self.pointer + 1 + i];
+            match parameter {
+                Parameter::Read => values.push(self.get_value(modes[i], p)?),
+                Parameter::Write => output = Some((modes[i], p)),
+            }
+        }
+        let v = (self.handlers.get_mut(&opcode).unwrap())(&values);
+        if let Some((mode, p)) = output {
+            self.set_value(mode, p, v)?;
+        }
+        self.pointer += 1 + parameters.len();
+        return Ok(());
+    }
+
+    pub fn get_pointer(&self) -> usize {
+        return self.pointer;
+    }
+
+    /// Opcode of the instruction `step` executes next, if the pointer is in
+    /// memory.
+    pub fn next_opcode(&self) -> Option<i64> {
+        let (opcode, _, _, _) = parse_instruction(*self.memory.get(self.pointer)?);
+        return Some(opcode);
+    }
+
+    /// Executes one instruction and returns whether it was a halt. A halt
+    /// leaves the pointer on it, so stepping again halts again. On a fault the
+    /// pointer stays on the faulting instruction.
+    pub fn step(&mut self) -> Result<bool, Fault> {
+        if self.pointer >= self.memory.len() {
+            return Err(Fault::InvalidAddress(self.pointer, self.pointer as i64));
+        }
+        if let Some(tracer) = &mut self.tracer {
+            tracer.trace(
+                self.pointer,
+                self.relative_base,
+                &self.memory,
+                &self.dialect,
+            );
+        }
+        let (opcode, am, bm, cm) = parse_instruction(self.memory[self.pointer]);
+        let count = match self.dialect.parameter_count(opcode) {
+            Some(count) => count,
+            None => return Err(Fault::InvalidOpcode(self.pointer)),
+        };
+        if self.pointer + count >= self.memory.len() {
+            let end = (self.pointer + count) as i64;
+            return Err(Fault::InvalidAddress(self.pointer, end));
+        }
+        match opcode {
+            INSTRUCTION_ADD => self.add(am, bm, cm)?,
+            INSTRUCTION_MUL => self.mul(am, bm, cm)?,
+            INSTRUCTION_READ => self.read(am)?,
+            INSTRUCTION_WRITE => self.write(am)?,
+            INSTRUCTION_JUMP_IF_TRUE => self.jump_if_true(am, bm)?,
+            INSTRUCTION_JUMP_IF_FALSE => self.jump_if_false(am, bm)?,
+            INSTRUCTION_LESS_THAN => self.less_than(am, bm, cm)?,
+            INSTRUCTION_EQUALS => self.equals(am, bm, cm)?,
+            INSTRUCTION_RELATIVE_ADD => self.relative_add(am)?,
+            INSTRUCTION_EXIT => {}
+            _ => self.custom(opcode, [am, bm, cm])?,
+        }
+        // Only instructions that completed are counted.
+        self.instruction_count += 1;
+        return Ok(opcode == INSTRUCTION_EXIT);
     }
 }
 
 impl Computer for IntComputer {
     fn execute(&mut self) -> i8 {
+        self.fault = None;
+        loop {
+            match self.step() {
+                Ok(false) => {}
+                Ok(true) => return 0,
+                Err(fault) => {
+                    self.fault = Some(fault);
+                    return -1;
+                }
+            }
+        }
+    }
+
+    fn execute_with_limits(&mut self, limits: &Limits) -> Outcome {
+        self.fault = None;
+        let mut executed: u64 = 0;
         loop {
-            let (opcode, am, bm, cm) = parse_instruction(self.memory[self.pointer]);
-            match opcode {
-                INSTRUCTION_ADD => self.add(am, bm, cm),
-                INSTRUCTION_MUL => self.mul(am, bm, cm),
-                INSTRUCTION_READ => self.read(am),
-                INSTRUCTION_WRITE => self.write(am),
-                INSTRUCTION_JUMP_IF_TRUE => self.jump_if_true(am, bm),
-                INSTRUCTION_JUMP_IF_FALSE => self.jump_if_false(am, bm),
-                INSTRUCTION_LESS_THAN => self.less_than(am, bm, cm),
-                INSTRUCTION_EQUALS => self.equals(am, bm, cm),
-                INSTRUCTION_RELATIVE_ADD => self.relative_add(am),
-                INSTRUCTION_EXIT => return 0,
-                _ => return -1,
+            if let Some(cancel) = &limits.cancel {
+                if cancel.load(Ordering::Relaxed) {
+                    return Outcome::LimitExceeded(Limit::Cancelled);
+                }
+            }
+            if let Some(deadline) = limits.deadline {
+                if executed % DEADLINE_INTERVAL == 0 && Instant::now() >= deadline {
+                    return Outcome::LimitExceeded(Limit::Deadline);
+                }
+            }
+            if limits.max_instructions == Some(executed) {
+                return Outcome::LimitExceeded(Limit::Instructions);
+            }
+            match self.step() {
+                Ok(false) => executed += 1,
+                Ok(true) => return Outcome::Halted,
+                Err(fault) => {
+                    self.fault = Some(fault);
+                    return Outcome::Fault(fault);
+                }
             }
         }
     }
 
+    fn get_fault(&self) -> Option<Fault> {
+        return self.fault;
+    }
+
     fn get_memory_at(&self, position: usize) -> i64 {
         return self.memory[position];
     }
 
+    fn get_memory(&self) -> &[i64] {
+        return &self.memory;
+    }
+
+    fn get_instruction_count(&self) -> u64 {
+        return self.instruction_count;
+    }
+
     fn set_reader(&mut self, reader: Box<dyn Reader>) {
         self.reader = Option::from(reader);
     }
@@ -174,9 +501,80 @@ impl Computer for IntComputer {
     fn register_writer(&mut self, writer: Box<dyn Writer>) {
         self.writers.push(writer);
     }
+
+    fn clear_writers(&mut self) {
+        self.writers.clear();
+    }
+
+    fn take_writers(&mut self) -> Vec<Box<dyn Writer>> {
+        return self.writers.drain(..).collect();
+    }
+
+    fn take_reader(&mut self) -> Option<Box<dyn Reader>> {
+        return self.reader.take();
+    }
+
+    fn set_tracer(&mut self, tracer: Box<dyn Tracer>) {
+        self.tracer = Option::from(tracer);
+    }
+
+    fn register_opcode(&mut self, opcode: Opcode, handler: Handler) {
+        let code = opcode.opcode;
+        self.dialect.add(opcode);
+        self.handlers.insert(code, handler);
+    }
+
+    fn map_device(&mut self, start: usize, device: Box<dyn Device>) {
+        let size = device.size();
+        let end = start + size;
+        assert!(end <= self.memory.len(), "Device does not fit in memory");
+        for (other, other_size, _) in &self.devices {
+            assert!(
+                end <= *other || *other + *other_size <= start,
+                "Device overlaps another one"
+            );
+        }
+        self.devices.push((start, size, device));
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        return Snapshot {
+            memory: self.memory.clone(),
+            pointer: self.pointer,
+            relative_base: self.relative_base,
+            instruction_count: self.instruction_count,
+            devices: self
+                .devices
+                .iter()
+                .map(|(_, _, device)| device.snapshot())
+                .collect(),
+        };
+    }
+
+    /// Devices mapped after the snapshot was taken keep their state.
+    fn restore(&mut self, snapshot: &Snapshot) {
+        self.memory = snapshot.memory.clone();
+        self.pointer = snapshot.pointer;
+        self.relative_base = snapshot.relative_base;
+        self.instruction_count = snapshot.instruction_count;
+        self.fault = None;
+        for ((_, _, device), state) in self.devices.iter_mut().zip(&snapshot.devices) {
+            device.restore(state);
+        }
+    }
+}
+
+pub fn parameter_count(opcode: i64) -> Option<usize> {
+    match opcode {
+        INSTRUCTION_ADD | INSTRUCTION_MUL | INSTRUCTION_LESS_THAN | INSTRUCTION_EQUALS => Some(3),
+        INSTRUCTION_JUMP_IF_TRUE | INSTRUCTION_JUMP_IF_FALSE => Some(2),
+        INSTRUCTION_READ | INSTRUCTION_WRITE | INSTRUCTION_RELATIVE_ADD => Some(1),
+        INSTRUCTION_EXIT => Some(0),
+        _ => None,
+    }
 }
 
-fn parse_instruction(mut instruction: i64) -> (i64, i64, i64, i64) {
+pub fn parse_instruction(mut instruction: i64) -> (i64, i64, i64, i64) {
     let opcode = instruction % 100;
     instruction /= 100;
     let am = instruction % 10;
@@ -190,37 +588,81 @@ fn parse_instruction(mut instruction: i64) -> (i64, i64, i64, i64) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::device;
+    use crate::generator::Program;
+    use crate::reader;
+    use crate::tracer;
+    use crate::writer;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+    use std::sync::mpsc::channel;
+
+    fn run(computer: &mut IntComputer, inputs: &[i64]) -> (i8, Vec<i64>) {
+        let (tx, rx) = channel();
+        computer.set_reader(Box::new(reader::sequence(inputs)));
+        computer.writers.clear();
+        computer.register_writer(Box::new(writer::channel(tx)));
+        let status = computer.execute();
+        return (status, rx.try_iter().collect());
+    }
+
+    #[quickcheck]
+    fn test_execute_halts(program: Program) -> bool {
+        let mut computer = new(Level::Day9, program.code.len(), &program.code);
+        let (status, _) = run(&mut computer, &program.inputs);
+        status == 0 && computer.memory[computer.pointer] == INSTRUCTION_EXIT
+    }
+
+    #[quickcheck]
+    fn test_execute_deterministic(program: Program) -> bool {
+        let mut first = new(Level::Day9, program.code.len(), &program.code);
+        let mut second = new(Level::Day9, program.code.len(), &program.code);
+        run(&mut first, &program.inputs) == run(&mut second, &program.inputs)
+            && first.snapshot() == second.snapshot()
+    }
+
+    #[quickcheck]
+    fn test_snapshot_restore(program: Program) -> bool {
+        let mut computer = new(Level::Day9, program.code.len(), &program.code);
+        let initial = computer.snapshot();
+        let first = run(&mut computer, &program.inputs);
+        let halted = computer.snapshot();
+        computer.restore(&initial);
+        assert_eq!(computer.snapshot(), initial);
+        let second = run(&mut computer, &program.inputs);
+        first == second && computer.snapshot() == halted
+    }
 
     #[quickcheck]
     fn test_add_immediate(a: i64, b: i64) -> bool {
-        let mut computer = new(4, &vec![INSTRUCTION_ADD, a, b, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_ADD, a, b, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.add(1, 1, 0);
+        computer.add(1, 1, 0).unwrap();
         assert_eq!(computer.pointer, 4);
         computer.memory[0] == a + b
     }
     #[test]
     fn test_add_position() {
-        let mut computer = new(4, &vec![INSTRUCTION_ADD, 0, 0, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_ADD, 0, 0, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.add(0, 0, 0);
+        computer.add(0, 0, 0).unwrap();
         assert_eq!(computer.pointer, 4);
         assert_eq!(computer.memory[0], 2);
     }
 
     #[quickcheck]
     fn test_mul_immediate(a: i64, b: i64) -> bool {
-        let mut computer = new(4, &vec![INSTRUCTION_MUL, a, b, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_MUL, a, b, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.mul(1, 1, 0);
+        computer.mul(1, 1, 0).unwrap();
         assert_eq!(computer.pointer, 4);
         computer.memory[0] == a * b
     }
     #[test]
     fn test_mul_position() {
-        let mut computer = new(4, &vec![INSTRUCTION_MUL, 0, 0, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_MUL, 0, 0, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.mul(0, 0, 0);
+        computer.mul(0, 0, 0).unwrap();
         assert_eq!(computer.pointer, 4);
         assert_eq!(computer.memory[0], 4);
     }
@@ -235,10 +677,10 @@ mod tests {
                 return self.v;
             }
         }
-        let mut computer = new(4, &vec![INSTRUCTION_READ, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_READ, 0]);
         computer.set_reader(Box::from(TestReader { v: v }));
         assert_eq!(computer.pointer, 0);
-        computer.read(0);
+        computer.read(0).unwrap();
         assert_eq!(computer.pointer, 2);
         computer.memory[0] == v
     }
@@ -253,12 +695,12 @@ mod tests {
                 assert_eq!(self.expected, value);
             }
         }
-        let mut computer = new(5, &vec![INSTRUCTION_WRITE, 0]);
+        let mut computer = new(Level::Day9, 5, &[INSTRUCTION_WRITE, 0]);
         computer.register_writer(Box::from(TestWriter { expected: 0 }));
         computer.register_writer(Box::from(TestWriter { expected: 0 }));
         computer.register_writer(Box::from(TestWriter { expected: 0 }));
         assert_eq!(computer.pointer, 0);
-        computer.write(1);
+        computer.write(1).unwrap();
         assert_eq!(computer.pointer, 2);
     }
     #[test]
@@ -271,66 +713,288 @@ mod tests {
                 assert_eq!(self.expected, value);
             }
         }
-        let mut computer = new(5, &vec![INSTRUCTION_WRITE, 0]);
+        let mut computer = new(Level::Day9, 5, &[INSTRUCTION_WRITE, 0]);
         computer.register_writer(Box::from(TestWriter { expected: 4 }));
         computer.register_writer(Box::from(TestWriter { expected: 4 }));
         computer.register_writer(Box::from(TestWriter { expected: 4 }));
         assert_eq!(computer.pointer, 0);
-        computer.write(0);
+        computer.write(0).unwrap();
         assert_eq!(computer.pointer, 2);
     }
 
     #[test]
     fn test_jump_if_true() {
-        let mut computer = new(4, &vec![INSTRUCTION_JUMP_IF_TRUE, 0, 3, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_JUMP_IF_TRUE, 0, 3, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.jump_if_true(0, 0);
+        computer.jump_if_true(0, 0).unwrap();
         assert_eq!(computer.pointer, 0);
-        computer.jump_if_true(1, 1);
+        computer.jump_if_true(1, 1).unwrap();
         assert_eq!(computer.pointer, 3);
     }
 
     #[test]
     fn test_jump_if_false() {
-        let mut computer = new(4, &vec![INSTRUCTION_JUMP_IF_FALSE, 0, 3, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_JUMP_IF_FALSE, 0, 3, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.jump_if_false(1, 0);
+        computer.jump_if_false(1, 0).unwrap();
         assert_eq!(computer.pointer, 0);
-        computer.jump_if_false(0, 1);
+        computer.jump_if_false(0, 1).unwrap();
         assert_eq!(computer.pointer, 3);
     }
 
     #[quickcheck]
     fn test_less_than_immediate(a: i64, b: i64) -> bool {
-        let mut computer = new(4, &vec![INSTRUCTION_LESS_THAN, a, b, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_LESS_THAN, a, b, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.less_than(1, 1, 0);
+        computer.less_than(1, 1, 0).unwrap();
         assert_eq!(computer.pointer, 4);
         computer.memory[0] == if a < b { 1 } else { 0 }
     }
     #[test]
     fn test_less_than_position() {
-        let mut computer = new(4, &vec![INSTRUCTION_LESS_THAN, 3, 1, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_LESS_THAN, 3, 1, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.less_than(0, 0, 0);
+        computer.less_than(0, 0, 0).unwrap();
         assert_eq!(computer.pointer, 4);
         assert_eq!(computer.memory[0], 1);
     }
 
     #[quickcheck]
     fn test_equals_immediate(a: i64, b: i64) -> bool {
-        let mut computer = new(4, &vec![INSTRUCTION_EQUALS, a, b, 0]);
+        let mut computer = new(Level::Day9, 4, &[INSTRUCTION_EQUALS, a, b, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.equals(1, 1, 0);
+        computer.equals(1, 1, 0).unwrap();
         assert_eq!(computer.pointer, 4);
         computer.memory[0] == if a == b { 1 } else { 0 }
     }
     #[test]
     fn test_equals_position() {
-        let mut computer = new(5, &vec![INSTRUCTION_EQUALS, 3, 4, 0]);
+        let mut computer = new(Level::Day9, 5, &[INSTRUCTION_EQUALS, 3, 4, 0]);
         assert_eq!(computer.pointer, 0);
-        computer.equals(0, 0, 0);
+        computer.equals(0, 0, 0).unwrap();
         assert_eq!(computer.pointer, 4);
         assert_eq!(computer.memory[0], 1);
     }
+
+    #[test]
+    fn test_faults() {
+        let cases: Vec<(&[i64], Fault)> = vec![
+            (&[42], Fault::InvalidOpcode(0)),
+            (&[104, 1], Fault::InvalidAddress(2, 2)),
+            (&[1101, 1, 1], Fault::InvalidAddress(0, 3)),
+            (&[1105, 1, 4, 0], Fault::InvalidAddress(0, 4)),
+            (&[1105, 1, -1, 0], Fault::InvalidAddress(0, -1)),
+            (&[4, -3, 99], Fault::InvalidAddress(0, -3)),
+            (&[3, 0, 99], Fault::NoReader(0)),
+        ];
+        for (program, fault) in cases {
+            let mut computer = new(Level::Day9, program.len(), program);
+            assert_eq!(computer.execute(), -1);
+            assert_eq!(computer.get_fault(), Some(fault));
+            assert_eq!(computer.pointer, fault_pointer(fault));
+        }
+    }
+
+    #[test]
+    fn test_faulting_read() {
+        // in [-1]
+        let program = [3, -1, 99];
+        let mut computer = new(Level::Day9, program.len(), &program);
+        let value = Rc::new(Cell::new(Some(7)));
+        computer.set_reader(Box::new(reader::slot(value.clone())));
+        assert_eq!(computer.execute(), -1);
+        assert_eq!(computer.get_fault(), Some(Fault::InvalidAddress(0, -1)));
+        assert_eq!(computer.instruction_count, 0);
+        assert_eq!(value.get(), Some(7));
+    }
+
+    fn fault_pointer(fault: Fault) -> usize {
+        match fault {
+            Fault::InvalidOpcode(p)
+            | Fault::InvalidMode(p)
+            | Fault::ImmediateWrite(p)
+            | Fault::InvalidAddress(p, _)
+            | Fault::Overflow(p)
+            | Fault::NoReader(p) => p,
+        }
+    }
+
+    #[test]
+    fn test_instruction_limit_resumes() {
+        // in [9]; out [9]; jnz 1, 0
+        let program = [3, 9, 4, 9, 1105, 1, 0, 0, 0, 0];
+        let mut computer = new(Level::Day9, program.len(), &program);
+        let (tx, rx) = channel();
+        computer.set_reader(Box::new(reader::sequence(&[1, 2, 3])));
+        computer.register_writer(Box::new(writer::channel(tx)));
+        let limits = Limits {
+            max_instructions: Some(4),
+            ..Default::default()
+        };
+        let outcome = computer.execute_with_limits(&limits);
+        assert_eq!(outcome, Outcome::LimitExceeded(Limit::Instructions));
+        assert_eq!(computer.pointer, 2);
+        assert_eq!(computer.instruction_count, 4);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![1]);
+
+        computer.execute_with_limits(&limits);
+        assert_eq!(computer.pointer, 4);
+        assert_eq!(computer.instruction_count, 8);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_deadline_and_cancel() {
+        let program = [1105, 1, 0];
+        let mut computer = new(Level::Day9, program.len(), &program);
+        let limits = Limits {
+            deadline: Some(Instant::now() + std::time::Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let outcome = computer.execute_with_limits(&limits);
+        assert_eq!(outcome, Outcome::LimitExceeded(Limit::Deadline));
+        assert!(computer.instruction_count > 0);
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let trigger = cancel.clone();
+        let handle = std::thread::spawn(move || {
+            let mut computer = new(Level::Day9, program.len(), &program);
+            let limits = Limits {
+                cancel: Some(cancel),
+                ..Default::default()
+            };
+            return computer.execute_with_limits(&limits);
+        });
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        trigger.store(true, Ordering::Relaxed);
+        assert_eq!(
+            handle.join().unwrap(),
+            Outcome::LimitExceeded(Limit::Cancelled)
+        );
+    }
+
+    #[quickcheck]
+    fn test_limits_match_execute(program: Program, limit: u64) -> bool {
+        let mut limited = new(Level::Day9, program.code.len(), &program.code);
+        limited.set_reader(Box::new(reader::sequence(&program.inputs)));
+        let limits = Limits {
+            max_instructions: Some(limit % 8 + 1),
+            ..Default::default()
+        };
+        while limited.execute_with_limits(&limits) != Outcome::Halted {}
+        let mut plain = new(Level::Day9, program.code.len(), &program.code);
+        plain.set_reader(Box::new(reader::sequence(&program.inputs)));
+        plain.execute();
+        limited.snapshot() == plain.snapshot()
+    }
+
+    #[test]
+    fn test_custom_opcodes() {
+        let printed = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let sink = printed.clone();
+        // hyp 3, [rb+7], [8]; dbg [8]; hlt
+        let program = [2120, 3, 7, 8, 21, 8, 99, 4, 0];
+        let mut computer = new(Level::Day9, program.len(), &program);
+        computer.register_opcode(
+            Opcode {
+                opcode: 20,
+                mnemonic: "hyp",
+                parameters: vec![Parameter::Read, Parameter::Read, Parameter::Write],
+            },
+            Box::new(|values| values[0] * values[0] + values[1] * values[1]),
+        );
+        computer.register_opcode(
+            Opcode {
+                opcode: 21,
+                mnemonic: "dbg",
+                parameters: vec![Parameter::Read],
+            },
+            Box::new(move |values| {
+                sink.borrow_mut().push(values[0]);
+                return 0;
+            }),
+        );
+        computer.set_tracer(Box::new(tracer::recorder(log.clone())));
+        assert_eq!(computer.execute(), 0);
+        assert_eq!(*printed.borrow(), vec![25]);
+        assert!(log.borrow()[0].ends_with("hyp 3, [rb+7], [8]"));
+        assert!(log.borrow()[1].ends_with("dbg [8]"));
+    }
+
+    #[test]
+    fn test_levels() {
+        let cases: Vec<(Level, &[i64], Option<Fault>)> = vec![
+            (Level::Day2, &[1, 0, 0, 0, 99], None),
+            (
+                Level::Day2,
+                &[1101, 1, 1, 0, 99],
+                Some(Fault::InvalidMode(0)),
+            ),
+            (Level::Day2, &[104, 1, 99], Some(Fault::InvalidOpcode(0))),
+            (Level::Day5, &[1101, 1, 1, 0, 104, 1, 99], None),
+            (Level::Day5, &[109, 1, 99], Some(Fault::InvalidOpcode(0))),
+            (Level::Day5, &[204, 1, 99], Some(Fault::InvalidMode(0))),
+            (Level::Day9, &[109, 1, 204, 1, 99], None),
+        ];
+        for (level, program, fault) in cases {
+            let (tx, _rx) = channel();
+            let mut computer = new(level, program.len(), program);
+            computer.register_writer(Box::new(writer::channel(tx)));
+            let status = computer.execute();
+            assert_eq!(computer.get_fault(), fault, "{:?} {:?}", level, program);
+            assert_eq!(status, if fault.is_some() { -1 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn test_devices() {
+        let pixels = Rc::new(RefCell::new(vec![0; 4]));
+        let (tx, rx) = channel();
+        // add 7, 9, [11]; out [11]; out [14]; out [14]; hlt
+        let program = [1101, 7, 9, 11, 4, 11, 4, 14, 4, 14, 99];
+        let mut computer = new(Level::Day9, 20, &program);
+        computer.map_device(10, Box::new(device::framebuffer(pixels.clone())));
+        computer.map_device(14, Box::new(device::random(1)));
+        computer.register_writer(Box::new(writer::channel(tx)));
+        assert_eq!(computer.execute(), 0);
+        assert_eq!(*pixels.borrow(), vec![0, 16, 0, 0]);
+        assert_eq!(computer.get_memory()[11], 16);
+
+        let outputs: Vec<i64> = rx.try_iter().collect();
+        assert_eq!(outputs[0], 16);
+        assert_ne!(outputs[1], outputs[2]);
+    }
+
+    #[test]
+    fn test_restore_devices() {
+        let pixels = Rc::new(RefCell::new(vec![0; 2]));
+        let (tx, rx) = channel();
+        // add [14], 0, [10]; out [10]; hlt
+        let program = [1001, 14, 0, 10, 4, 10, 99];
+        let mut computer = new(Level::Day9, 20, &program);
+        computer.map_device(10, Box::new(device::framebuffer(pixels.clone())));
+        computer.map_device(14, Box::new(device::random(1)));
+        computer.register_writer(Box::new(writer::channel(tx)));
+        let initial = computer.snapshot();
+        assert_eq!(computer.execute(), 0);
+        let first = pixels.borrow().clone();
+
+        computer.restore(&initial);
+        assert_eq!(*pixels.borrow(), vec![0, 0]);
+        assert_eq!(computer.get_memory()[10], 0);
+        assert_eq!(computer.execute(), 0);
+        assert_eq!(*pixels.borrow(), first);
+        let outputs: Vec<i64> = rx.try_iter().collect();
+        assert_eq!(outputs, [first[0], first[0]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Device overlaps another one")]
+    fn test_overlapping_devices() {
+        let mut computer = new(Level::Day9, 20, &[99]);
+        computer.map_device(10, Box::new(device::clock()));
+        let pixels = Rc::new(RefCell::new(vec![0; 4]));
+        computer.map_device(8, Box::new(device::framebuffer(pixels)));
+    }
 }
@@ -1,32 +1,40 @@
-use std::fs;
+use crate::days::Solution;
+use crate::parse;
 
-pub fn run(input: &str) {
-    let inputs = load_inputs(input);
-    part_1(&inputs);
-    part_2(&inputs);
-}
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = Vec<f64>;
+    type Answer1 = f64;
+    type Answer2 = f64;
+
+    fn parse(&self, text: &str) -> parse::Result<Vec<f64>> {
+        return parse::lines(text, parse::number);
+    }
 
-fn load_inputs(file_name: &str) -> Vec<f64> {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    return content.lines().map(|x| x.parse::<f64>().unwrap()).collect();
+    fn part_1(&self, input: &Vec<f64>) -> f64 {
+        return part_1(input);
+    }
+
+    fn part_2(&self, input: &Vec<f64>) -> f64 {
+        return part_2(input);
+    }
 }
 
 fn fuel_calculation(mass: &f64) -> f64 {
     return (mass / 3.0).floor() - 2.0;
 }
 
-fn part_1(inputs: &Vec<f64>) {
-    let fuel: f64 = inputs.iter().map(fuel_calculation).sum();
-    println!("Part 1: Fuel mass {}", fuel);
+fn part_1(inputs: &[f64]) -> f64 {
+    return inputs.iter().map(fuel_calculation).sum();
 }
 
-fn part_2(inputs: &Vec<f64>) {
-    let fuel: f64 = inputs
+fn part_2(inputs: &[f64]) -> f64 {
+    return inputs
         .iter()
         .map(fuel_calculation)
         .map(|x| x + additional_fuel_calculation(x))
         .sum();
-    println!("Part 2: Fuel mass {}", fuel);
 }
 
 fn additional_fuel_calculation(fuel_weight: f64) -> f64 {
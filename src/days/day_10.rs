@@ -1,96 +1,111 @@
+use crate::days::Solution;
+use crate::grid;
+use crate::grid::{Grid, Point};
+use crate::parse;
 use num::integer::gcd;
-use std::collections::{BTreeMap, HashSet};
-use std::fs;
+use std::collections::BTreeMap;
 
-#[derive(Debug)]
-struct Star {
-    x: i32,
-    y: i32,
+pub struct Map {
+    stars: Vec<Point>,
+    collisions: Grid<bool>,
 }
 
-struct Map {
-    stars: Vec<Star>,
-    collisions: HashSet<(i32, i32)>,
-}
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = Map;
+    type Answer1 = u32;
+    type Answer2 = i64;
+
+    fn parse(&self, text: &str) -> parse::Result<Map> {
+        return load_inputs(text);
+    }
+
+    // Maps without asteroids are rejected when parsing.
+    fn part_1(&self, input: &Map) -> u32 {
+        return part_1(input).expect("No asteroids").1;
+    }
 
-pub fn run(input: &str) {
-    let inputs = load_inputs(input);
-    let (x, y) = part_1(&inputs);
-    part_2(&inputs, x, y);
+    fn part_2(&self, input: &Map) -> i64 {
+        // The station goes where part 1 found the best spot.
+        let (station, _) = part_1(input).expect("No asteroids");
+        return part_2(input, station);
+    }
 }
 
-fn load_inputs(file_name: &str) -> Map {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    let rows: Vec<&str> = content.lines().collect();
-    let mut stars = Vec::new();
-    let mut collisions = HashSet::new();
-    for (y, row) in rows.iter().enumerate() {
-        let cols: Vec<char> = row.chars().collect();
-        for (x, c) in cols.iter().enumerate() {
-            if *c == '#' {
-                stars.push(Star {
-                    x: x as i32,
-                    y: y as i32,
-                });
-                collisions.insert((x as i32, y as i32));
-            }
-        }
+fn load_inputs(content: &str) -> parse::Result<Map> {
+    let collisions = grid::parse(content, |c| match c {
+        '#' => Ok(true),
+        '.' => Ok(false),
+        _ => Err(format!("expected '#' or '.': '{}'", c)),
+    })?;
+    let stars: Vec<Point> = collisions
+        .iter()
+        .filter(|(_, star)| **star)
+        .map(|(point, _)| point)
+        .collect();
+    if stars.is_empty() {
+        return Err(parse::error(1, 1, "expected at least one asteroid"));
     }
 
-    assert_eq!(stars.len(), collisions.len());
-    let width = rows[0].len() as u8;
-    let height = rows.len() as u8;
-    println!("Map w {} x h {} with {} stars", width, height, stars.len());
-    return Map {
+    return Ok(Map {
         stars: stars,
         collisions: collisions,
-    };
+    });
 }
 
-fn part_1(map: &Map) -> (i32, i32) {
+/// The asteroid that sees the most others and how many, if there is any.
+fn part_1(map: &Map) -> Option<(Point, u32)> {
     let mut best_visible_stars = 0;
-    let mut best_position = -1;
-    for (i, star) in (&map.stars).iter().enumerate() {
+    let mut best_position: Option<usize> = None;
+    for (i, star) in map.stars.iter().enumerate() {
         let mut visible_stars = 0;
-        for (j, other) in (&map.stars).iter().enumerate() {
+        for (j, other) in map.stars.iter().enumerate() {
             if i == j {
                 continue;
             }
 
-            if is_visible(&map.collisions, star.x, star.y, other) {
+            if is_visible(&map.collisions, *star, *other) {
                 visible_stars += 1;
             }
         }
-        if best_visible_stars < visible_stars {
+        if best_position.is_none() || best_visible_stars < visible_stars {
             best_visible_stars = visible_stars;
-            best_position = i as i32;
+            best_position = Some(i);
         }
     }
-    let best_star = &map.stars[best_position as usize];
-    println!(
-        "Best star with {} visible is {:?}",
-        best_visible_stars, best_star
-    );
-    return (best_star.x, best_star.y);
+    return best_position.map(|i| (map.stars[i], best_visible_stars));
+}
+
+/// The 200th asteroid the laser vaporizes, as x * 100 + y.
+fn part_2(map: &Map, station: Point) -> i64 {
+    let order = vaporize(map, station);
+    let star = order
+        .get(199)
+        .expect("Fewer than 200 asteroids to vaporize");
+    return star.x * 100 + star.y;
 }
 
-fn part_2(map: &Map, x: i32, y: i32) {
+/// Every asteroid in the order a laser at the station vaporizes them,
+/// rotating clockwise from straight up.
+fn vaporize(map: &Map, station: Point) -> Vec<Point> {
     let mut stars = BTreeMap::new();
     for star in &map.stars {
-        if star.x == x && star.y == y {
+        if *star == station {
             continue;
         }
 
-        let dx = star.x as f64 - x as f64;
-        let dy = star.y as f64 - y as f64;
-        let d = dy.atan2(dx) + std::f64::consts::FRAC_PI_2;
-        // if star.x == 11 && star.y == 12 {
-        //     println!("11,12 -> {}", d);
-        // }
+        let dx = (star.x - station.x) as f64;
+        let dy = (star.y - station.y) as f64;
+        // Angles go clockwise from straight up, from 0 to 2 pi.
+        let mut d = dy.atan2(dx) + std::f64::consts::FRAC_PI_2;
+        if d < 0.0 {
+            d += 2.0 * std::f64::consts::PI;
+        }
         stars.insert(
             (
                 d.trunc() as i32,
-                (d.fract() * 100000000000.0 as f64) as i64,
+                (d.fract() * 100000000000.0_f64) as i64,
                 star.x,
                 star.y,
             ),
@@ -100,55 +115,33 @@ fn part_2(map: &Map, x: i32, y: i32) {
     assert_eq!(map.stars.len(), stars.len() + 1);
 
     let mut collisions = map.collisions.clone();
+    let mut order = Vec::new();
     let mut destroyed = 0;
-    let mut last_destroyed_trunc: i32 = 0;
-    let mut last_destroyed_fract: i64 = 0;
-    loop {
+    while destroyed < stars.len() {
+        // Only the nearest asteroid in a direction goes each rotation.
+        let mut last_destroyed = None;
         for ((t, f, _, _), star) in stars.iter() {
-            if destroyed == 0 && (t < &0 || f < &0) {
-                continue;
-            }
-            if !collisions.contains(&(star.x, star.y)) {
+            if !collisions[**star] {
                 continue;
             }
-            if !is_visible(&collisions, x, y, star) {
+            if !is_visible(&collisions, station, **star) {
                 continue;
             }
-            if destroyed > 0 && t == &last_destroyed_trunc && f == &last_destroyed_fract {
+            if last_destroyed == Some((t, f)) {
                 continue;
             }
-            last_destroyed_trunc = *t;
-            last_destroyed_fract = *f;
-            collisions.remove(&(star.x, star.y));
+            last_destroyed = Some((t, f));
+            collisions[**star] = false;
             destroyed += 1;
-            if destroyed <= 3
-                || destroyed == 10
-                || destroyed == 20
-                || destroyed == 50
-                || destroyed == 100
-                || destroyed == 199
-                || destroyed == 200
-            {
-                let dx = star.x as f64 - x as f64;
-                let dy = star.y as f64 - y as f64;
-                let d = dy.atan2(dx) + std::f64::consts::FRAC_PI_2;
-                println!("Destroyed {}: {:?} with angle: {}", destroyed, star, d,); // DEBUG
-                if destroyed == 200 {
-                    return;
-                }
-            }
+            order.push(**star);
         }
     }
+    return order;
 }
 
-fn is_visible(
-    collisions: &HashSet<(i32, i32)>,
-    origin_x: i32,
-    origin_y: i32,
-    destination: &Star,
-) -> bool {
-    let mut dx = destination.x - origin_x;
-    let mut dy = destination.y - origin_y;
+fn is_visible(collisions: &Grid<bool>, origin: Point, destination: Point) -> bool {
+    let mut dx = destination.x - origin.x;
+    let mut dy = destination.y - origin.y;
     assert!(dx != 0 || dy != 0);
 
     if dx == 0 {
@@ -161,15 +154,35 @@ fn is_visible(
         dy /= d;
     }
 
-    let mut x = origin_x + dx;
-    let mut y = origin_y + dy;
-    while x != destination.x || y != destination.y {
-        if collisions.contains(&(x, y)) {
+    let step = grid::point(dx, dy);
+    let mut point = origin + step;
+    while point != destination {
+        if collisions[point] {
             return false;
         }
-        x += dx;
-        y += dy;
+        point = point + step;
     }
 
     return true;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vaporize_up_left() {
+        let map = load_inputs("##.\n#..\n..#\n").unwrap();
+        assert_eq!(
+            vaporize(&map, grid::point(2, 2)),
+            [grid::point(0, 1), grid::point(0, 0), grid::point(1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_no_asteroids() {
+        assert!(load_inputs("...\n...\n").is_err());
+        let map = load_inputs("...\n.#.\n").unwrap();
+        assert_eq!(part_1(&map), Some((grid::point(1, 1), 0)));
+    }
+}
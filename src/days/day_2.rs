@@ -1,44 +1,53 @@
-use std::fs;
 use crate::computer;
-use crate::computer::{Computer};
-
-pub fn run(input: &str) {
-    let inputs = load_inputs(input);
-    let ans_1 = part_1(&inputs);
-    println!("Part 1: {}", ans_1);
-    let (noun, verb) = part_2(&inputs);
-    println!(
-        "Part 2: noun {} and verb {} -> {}",
-        noun,
-        verb,
-        noun * 100 + verb
-    );
-}
+use crate::computer::{Computer, Level};
+use crate::days::Solution;
+use crate::parse;
+use crate::symbolic;
+use crate::symbolic::Expr;
+
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = Vec<i64>;
+    type Answer1 = i64;
+    type Answer2 = i64;
 
-fn load_inputs(file_name: &str) -> Vec<i64> {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    return content
-        .split(",")
-        .map(|x| x.parse::<i64>().unwrap())
-        .collect();
+    fn parse(&self, text: &str) -> parse::Result<Vec<i64>> {
+        // The noun and the verb go in cells 1 and 2.
+        return parse::program_of(text, 3);
+    }
+
+    fn part_1(&self, input: &Vec<i64>) -> i64 {
+        return part_1(input);
+    }
+
+    fn part_2(&self, input: &Vec<i64>) -> i64 {
+        let (noun, verb) = part_2(input);
+        return noun * 100 + verb;
+    }
 }
 
-fn part_1(inputs: &Vec<i64>) -> i64 {
-    let mut instructions = inputs.clone();
+fn part_1(inputs: &[i64]) -> i64 {
+    let mut instructions = inputs.to_vec();
     instructions[1] = 12;
     instructions[2] = 2;
-    let mut computer = computer::new(instructions.len(), &instructions);
+    let mut computer = computer::new(Level::Day2, instructions.len(), &instructions);
     assert_eq!(0, computer.execute());
     return computer.get_memory_at(0);
 }
 
-fn part_2(inputs: &Vec<i64>) -> (i64, i64) {
+fn part_2(inputs: &[i64]) -> (i64, i64) {
+    if let Some(answer) = solve_symbolically(inputs) {
+        return answer;
+    }
+    // Programs that use the noun or verb as an address or a condition are
+    // searched one pair at a time, skipping the pairs whose run fails.
     for noun in 0..100 {
         for verb in 0..100 {
-            let mut instructions = inputs.clone();
+            let mut instructions = inputs.to_vec();
             instructions[1] = noun;
             instructions[2] = verb;
-            let mut computer = computer::new(instructions.len(), &instructions);
+            let mut computer = computer::new(Level::Day2, instructions.len(), &instructions);
             if computer.execute() != 0 {
                 continue;
             }
@@ -49,3 +58,16 @@ fn part_2(inputs: &Vec<i64>) -> (i64, i64) {
     }
     panic!("No answer found for part 2 :(");
 }
+
+fn solve_symbolically(inputs: &[i64]) -> Option<(i64, i64)> {
+    let mut machine = symbolic::new(inputs);
+    machine.set_variable(1, "noun");
+    machine.set_variable(2, "verb");
+    machine.execute().ok()?;
+    let output = match machine.get_memory_at(0) {
+        Expr::Linear(linear) => linear,
+        _ => return None,
+    };
+    let values = output.solve(19690720, &[("noun", 0, 99), ("verb", 0, 99)])?;
+    return Some((values[0], values[1]));
+}
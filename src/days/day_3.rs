@@ -1,62 +1,77 @@
-use std::collections::HashMap;
-use std::fs;
+use crate::days::Solution;
+use crate::grid;
+use crate::grid::{Direction, Point};
+use crate::parse;
 
 #[derive(Debug, Clone)]
 struct Path {
-    direction: String,
+    direction: Direction,
     distance: u32,
 }
 
 #[derive(Debug, Copy, Clone)]
-struct Step {
-    x: i32,
-    y: i32,
+pub struct Step {
+    point: Point,
     steps: u32,
 }
 
-pub fn run(input: &str) {
-    let (left_paths, right_paths) = load_inputs(input);
-    let left_steps = paths_to_steps(&left_paths);
-    let right_steps = paths_to_steps(&right_paths);
-    part_1(&left_steps, &right_steps);
-    part_2(&left_steps, &right_steps);
-}
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = (Vec<Step>, Vec<Step>);
+    type Answer1 = u32;
+    type Answer2 = u32;
+
+    fn parse(&self, text: &str) -> parse::Result<(Vec<Step>, Vec<Step>)> {
+        let (left_paths, right_paths) = load_inputs(text)?;
+        return Ok((paths_to_steps(&left_paths), paths_to_steps(&right_paths)));
+    }
+
+    fn part_1(&self, input: &(Vec<Step>, Vec<Step>)) -> u32 {
+        return part_1(&input.0, &input.1);
+    }
 
-fn load_inputs(file_name: &str) -> (Vec<Path>, Vec<Path>) {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    let both: Vec<&str> = content.lines().collect();
-    let left = both[0].split(",").map(string_to_path).collect();
-    let right = both[1].split(",").map(string_to_path).collect();
-    (left, right)
+    fn part_2(&self, input: &(Vec<Step>, Vec<Step>)) -> u32 {
+        return part_2(&input.0, &input.1);
+    }
 }
 
-fn string_to_path(path: &str) -> Path {
-    let (left, right) = path.split_at(1);
-    Path {
-        direction: String::from(left),
-        distance: right.parse().unwrap(),
+fn load_inputs(content: &str) -> parse::Result<(Vec<Path>, Vec<Path>)> {
+    let mut both = parse::lines(content, |line| parse::separated(line, ',', string_to_path))?;
+    if both.len() != 2 {
+        return Err(parse::error(both.len().min(2) + 1, 1, "expected two wires"));
     }
+    let right = both.pop().unwrap();
+    let left = both.pop().unwrap();
+    return Ok((left, right));
 }
 
-fn paths_to_steps(paths: &Vec<Path>) -> Vec<Step> {
+fn string_to_path(path: &str) -> parse::Result<Path> {
+    let (direction, distance) = parse::prefixed(path, parse::number)?;
+    let direction = match direction {
+        'U' => Direction::Up,
+        'D' => Direction::Down,
+        'L' => Direction::Left,
+        'R' => Direction::Right,
+        _ => return Err(parse::error(1, 1, "expected U, D, L or R")),
+    };
+    return Ok(Path {
+        direction: direction,
+        distance: distance,
+    });
+}
+
+fn paths_to_steps(paths: &[Path]) -> Vec<Step> {
     let mut steps: Vec<Step> = Vec::new();
 
-    let mut x = 0;
-    let mut y = 0;
+    let mut point = grid::ORIGIN;
     let mut d = 0;
     for path in paths {
         for _ in 0..path.distance {
-            match path.direction.as_ref() {
-                "U" => y += 1,
-                "D" => y -= 1,
-                "L" => x -= 1,
-                "R" => x += 1,
-                _ => panic!("Not valid direction"),
-            }
+            point = point.step(path.direction);
             d += 1;
             steps.push(Step {
-                x: x,
-                y: y,
+                point: point,
                 steps: d,
             });
         }
@@ -65,48 +80,42 @@ fn paths_to_steps(paths: &Vec<Path>) -> Vec<Step> {
     steps
 }
 
-fn part_1(left_steps: &Vec<Step>, right_steps: &Vec<Step>) {
-    let mut visited = HashMap::new();
+fn part_1(left_steps: &[Step], right_steps: &[Step]) -> u32 {
+    let mut visited = grid::sparse();
 
     for left in left_steps {
-        let key = format!("{},{}", left.x, left.y);
-        visited.insert(key, true);
+        visited.insert(left.point, true);
     }
 
-    let mut distance = std::u32::MAX;
+    let mut distance = u32::MAX;
     for right in right_steps {
-        let key = format!("{},{}", right.x, right.y);
-        let left_visited = *visited.get(&key).unwrap_or(&false);
-        if left_visited {
-            let d = (right.x.abs() + right.y.abs()) as u32;
+        if visited.contains(right.point) {
+            let d = right.point.manhattan(grid::ORIGIN) as u32;
             if d < distance {
                 distance = d;
             }
         }
     }
 
-    println!("Part 1: {}", distance)
+    distance
 }
 
-fn part_2(left_steps: &Vec<Step>, right_steps: &Vec<Step>) {
-    let mut visited = HashMap::new();
+fn part_2(left_steps: &[Step], right_steps: &[Step]) -> u32 {
+    let mut visited = grid::sparse();
 
     for left in left_steps {
-        let key = format!("{},{}", left.x, left.y);
-        visited.insert(key, left);
+        visited.insert(left.point, left);
     }
 
-    let mut distance = std::u32::MAX;
+    let mut distance = u32::MAX;
     for right in right_steps {
-        let key = format!("{},{}", right.x, right.y);
-        let left = visited.get(&key);
-        if left.is_some() {
-            let d = left.unwrap().steps + right.steps;
+        if let Some(left) = visited.get(right.point) {
+            let d = left.steps + right.steps;
             if d < distance {
                 distance = d;
             }
         }
     }
 
-    println!("Part 2: {}", distance)
+    distance
 }
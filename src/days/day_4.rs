@@ -1,6 +1,34 @@
-pub fn run() {
-    let mut part_1 = 0;
-    let mut part_2 = 0;
+use crate::days::Solution;
+use crate::parse;
+use std::ops::RangeInclusive;
+
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = RangeInclusive<u32>;
+    type Answer1 = u32;
+    type Answer2 = u32;
+
+    fn parse(&self, text: &str) -> parse::Result<RangeInclusive<u32>> {
+        let (low, high) = parse::line(text, |line| {
+            parse::pair(line, "-", parse::number, parse::number)
+        })?;
+        return Ok(low..=high);
+    }
+
+    fn part_1(&self, input: &RangeInclusive<u32>) -> u32 {
+        return count_passwords(input, false);
+    }
+
+    fn part_2(&self, input: &RangeInclusive<u32>) -> u32 {
+        return count_passwords(input, true);
+    }
+}
+
+/// Passwords of the range meeting the rules of part 1 or, with `exact_pair`,
+/// of part 2.
+fn count_passwords(range: &RangeInclusive<u32>, exact_pair: bool) -> u32 {
+    let mut count = 0;
 
     // It is a six-digit number.
     for p_1 in 0..10 {
@@ -18,7 +46,7 @@ pub fn run() {
                                 + p_4 * 100
                                 + p_5 * 10
                                 + p_6;
-                            if n < 156218 || 652527 < n {
+                            if !range.contains(&n) {
                                 continue;
                             }
 
@@ -33,7 +61,10 @@ pub fn run() {
                                 continue;
                             }
 
-                            part_1 += 1;
+                            if !exact_pair {
+                                count += 1;
+                                continue;
+                            }
 
                             // Only for part 2
 
@@ -65,7 +96,7 @@ pub fn run() {
                                 continue;
                             }
 
-                            part_2 += 1;
+                            count += 1;
                         }
                     }
                 }
@@ -73,6 +104,5 @@ pub fn run() {
         }
     }
 
-    println!("Part 1: {}", part_1);
-    println!("Part 2: {}", part_2);
+    return count;
 }
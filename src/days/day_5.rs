@@ -1,33 +1,54 @@
-use std::fs;
 use crate::computer;
-use crate::computer::{Computer};
+use crate::computer::{Computer, Level};
+use crate::days::Solution;
+use crate::parse;
 use crate::reader::once;
-use crate::writer::StdOut;
+use crate::writer;
+use std::cell::RefCell;
+use std::rc::Rc;
 
-pub fn run(input: &str) {
-    let inputs = load_inputs(input);
-    part_1(&inputs);
-    part_2(&inputs);
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = Vec<i64>;
+    type Answer1 = i64;
+    type Answer2 = i64;
+
+    fn parse(&self, text: &str) -> parse::Result<Vec<i64>> {
+        return parse::program(text);
+    }
+
+    fn part_1(&self, input: &Vec<i64>) -> i64 {
+        return part_1(input);
+    }
+
+    fn part_2(&self, input: &Vec<i64>) -> i64 {
+        return part_2(input);
+    }
 }
 
-fn load_inputs(file_name: &str) -> Vec<i64> {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    return content
-        .split(",")
-        .map(|x| x.parse::<i64>().unwrap())
-        .collect();
+fn part_1(memory: &[i64]) -> i64 {
+    return diagnose(memory, 1);
 }
 
-fn part_1(memory: &Vec<i64>) {
-    let mut computer = computer::new(memory.len(), memory);
-    computer.set_reader(Box::new(once(1)));
-    computer.register_writer(Box::new(StdOut{}));
-    assert_eq!(0, computer.execute());
+fn part_2(memory: &[i64]) -> i64 {
+    return diagnose(memory, 5);
 }
 
-fn part_2(memory: &Vec<i64>) {
-    let mut computer = computer::new(memory.len(), memory);
-    computer.set_reader(Box::new(once(5)));
-    computer.register_writer(Box::new(StdOut{}));
+/// Runs the diagnostic program for a system and returns its diagnostic code,
+/// which comes after a zero for every test that passed.
+fn diagnose(memory: &[i64], system: i64) -> i64 {
+    let outputs = Rc::new(RefCell::new(Vec::new()));
+    let mut computer = computer::new(Level::Day5, memory.len(), memory);
+    computer.set_reader(Box::new(once(system)));
+    computer.register_writer(Box::new(writer::buffer(outputs.clone())));
     assert_eq!(0, computer.execute());
+    let mut outputs = outputs.borrow_mut();
+    let code = outputs.pop().expect("No diagnostic code");
+    assert!(
+        outputs.iter().all(|o| *o == 0),
+        "Failed tests: {:?}",
+        outputs
+    );
+    return code;
 }
@@ -1,38 +1,51 @@
-use std::collections::HashMap;
-use std::fs;
+use crate::days::Solution;
+use crate::parse;
+use std::collections::{HashMap, HashSet};
 
-struct Node {
+pub struct Node {
     name: String,
     children: HashMap<String, Node>,
 }
 
-pub fn run(input: &str) {
-    let inputs = load_inputs(input);
-    part_1(&inputs);
-    part_2(&inputs);
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = Node;
+    type Answer1 = u32;
+    type Answer2 = u32;
+
+    fn parse(&self, text: &str) -> parse::Result<Node> {
+        return load_inputs(text);
+    }
+
+    fn part_1(&self, input: &Node) -> u32 {
+        return part_1(input);
+    }
+
+    fn part_2(&self, input: &Node) -> u32 {
+        return part_2(input);
+    }
 }
 
-fn load_inputs(file_name: &str) -> Node {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    let lines = content.lines();
+fn load_inputs(content: &str) -> parse::Result<Node> {
+    let edges = parse::lines(content, |line| {
+        parse::pair(line, ")", parse::name, parse::name)
+    })?;
+    check_orbits(&edges)?;
 
     let mut nodes: HashMap<&str, Node> = HashMap::new();
     let mut parents: HashMap<&str, &str> = HashMap::new();
-    for line in lines {
-        let node_names: Vec<&str> = line.split(")").collect();
-        assert_eq!(node_names.len(), 2);
-        let parent_node_name = node_names[0];
-        let child_node_name = node_names[1];
-
-        let child_node: Node;
-        if nodes.contains_key(child_node_name) {
-            child_node = nodes.remove(child_node_name).unwrap();
+    for (parent_node_name, child_node_name) in &edges {
+        let (parent_node_name, child_node_name) = (*parent_node_name, *child_node_name);
+
+        let child_node = if nodes.contains_key(child_node_name) {
+            nodes.remove(child_node_name).unwrap()
         } else {
-            child_node = Node {
+            Node {
                 name: String::from(child_node_name),
                 children: HashMap::new(),
-            };
-        }
+            }
+        };
 
         let parent_node: &mut Node;
         if nodes.contains_key(parent_node_name) {
@@ -63,31 +76,68 @@ fn load_inputs(file_name: &str) -> Node {
         parents.insert(child_node_name, parent_node_name);
     }
 
-    assert_eq!(nodes.len(), 1);
-    return nodes.remove("COM").unwrap();
+    // Only an empty map has no COM once the orbits are checked.
+    let com = match nodes.remove("COM") {
+        Some(com) => com,
+        None => return Err(parse::error(1, 1, "expected every object to orbit COM")),
+    };
+    for name in &["YOU", "SAN"] {
+        if find_child(&com, name, 0) == 0 {
+            return Err(parse::error(1, 1, &format!("expected {} in the map", name)));
+        }
+    }
+    return Ok(com);
 }
 
-fn part_1(node: &Node) {
-    let orbits = count_orbits(node, 0);
-    println!("Part 1: {}", orbits);
+/// Checks that the orbits form a single tree around COM, reporting the line
+/// of the first object orbiting twice, in a cycle or around nothing.
+fn check_orbits(edges: &[(&str, &str)]) -> parse::Result<()> {
+    let mut parents: HashMap<&str, (&str, usize)> = HashMap::new();
+    for (line, (parent, child)) in edges.iter().enumerate() {
+        if let Some((other, _)) = parents.insert(child, (parent, line + 1)) {
+            let message = format!("{} already orbits {}", child, other);
+            return Err(parse::error(line + 1, parent.len() + 2, &message));
+        }
+    }
+
+    for (line, (_, child)) in edges.iter().enumerate() {
+        let mut seen = HashSet::new();
+        let mut object = *child;
+        let mut at = line + 1;
+        while let Some((parent, parent_line)) = parents.get(object) {
+            if !seen.insert(object) {
+                return Err(parse::error(line + 1, 1, "orbits form a cycle"));
+            }
+            object = parent;
+            at = *parent_line;
+        }
+        if object != "COM" {
+            let message = format!("expected {} to orbit COM", object);
+            return Err(parse::error(at, 1, &message));
+        }
+    }
+    return Ok(());
+}
+
+fn part_1(node: &Node) -> u32 {
+    return count_orbits(node, 0);
 }
 
 fn count_orbits(node: &Node, depth: u32) -> u32 {
     let mut orbits = depth;
-    for (_, child) in &node.children {
+    for child in node.children.values() {
         orbits += count_orbits(child, depth + 1);
     }
     orbits
 }
 
-fn part_2(node: &Node) {
-    println!("Part 2");
+fn part_2(node: &Node) -> u32 {
     let mut parent = node;
 
     let mut got_both = true;
     while got_both {
         got_both = false;
-        for (_, child) in &parent.children {
+        for child in parent.children.values() {
             if find_child(child, "YOU", 0) != 0 && find_child(child, "SAN", 0) != 0 {
                 parent = child;
                 got_both = true;
@@ -96,15 +146,9 @@ fn part_2(node: &Node) {
         }
     }
 
-    println!("Common parent: {}", parent.name);
     let path_you = find_child(parent, "YOU", 0) - 1;
     let path_san = find_child(parent, "SAN", 0) - 1;
-    println!(
-        "Distance YOU = {}, distance SAN = {}, together: {}",
-        path_you,
-        path_san,
-        path_you + path_san
-    );
+    return path_you + path_san;
 }
 
 fn find_child(node: &Node, child_name: &str, depth: u32) -> u32 {
@@ -112,7 +156,7 @@ fn find_child(node: &Node, child_name: &str, depth: u32) -> u32 {
         return depth;
     }
 
-    for (_, child) in &node.children {
+    for child in node.children.values() {
         let d = find_child(child, child_name, depth + 1);
         if d > 0 {
             return d;
@@ -121,3 +165,41 @@ fn find_child(node: &Node, child_name: &str, depth: u32) -> u32 {
 
     return 0;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_orbits() {
+        let error = |text| load_inputs(text).err();
+        assert_eq!(
+            error("COM)B\nA)B"),
+            Some(parse::error(2, 3, "B already orbits COM"))
+        );
+        assert_eq!(
+            error("A)B\nB)A"),
+            Some(parse::error(1, 1, "orbits form a cycle"))
+        );
+        assert_eq!(
+            error("COM)B\nC)D\nD)C"),
+            Some(parse::error(2, 1, "orbits form a cycle"))
+        );
+        assert_eq!(
+            error("COM)B\nB)YOU\nX)SAN"),
+            Some(parse::error(3, 1, "expected X to orbit COM"))
+        );
+        assert_eq!(
+            error("A)B\nB)YOU"),
+            Some(parse::error(1, 1, "expected A to orbit COM"))
+        );
+        assert_eq!(
+            error(""),
+            Some(parse::error(1, 1, "expected every object to orbit COM"))
+        );
+        assert_eq!(
+            error("COM)B\nB)YOU"),
+            Some(parse::error(1, 1, "expected SAN in the map"))
+        );
+    }
+}
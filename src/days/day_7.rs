@@ -1,27 +1,35 @@
+use crate::asynchronous;
+use crate::asynchronous::Sink;
 use crate::computer;
-use crate::computer::Computer;
+use crate::computer::{Computer, Level};
+use crate::days::Solution;
+use crate::parse;
 use crate::reader;
 use crate::writer;
-use std::fs;
 use std::sync::mpsc::channel;
-use std::thread;
 use std::time::Duration;
 
-pub fn run(input: &str) {
-    let inputs = load_inputs(input);
-    part_1(&inputs);
-    part_2(&inputs);
-}
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = Vec<i64>;
+    type Answer1 = i64;
+    type Answer2 = i64;
+
+    fn parse(&self, text: &str) -> parse::Result<Vec<i64>> {
+        return parse::program(text);
+    }
 
-fn load_inputs(file_name: &str) -> Vec<i64> {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    return content
-        .split(",")
-        .map(|x| x.parse::<i64>().unwrap())
-        .collect();
+    fn part_1(&self, input: &Vec<i64>) -> i64 {
+        return part_1(input);
+    }
+
+    fn part_2(&self, input: &Vec<i64>) -> i64 {
+        return part_2(input);
+    }
 }
 
-fn part_1(memory: &Vec<i64>) {
+fn part_1(memory: &[i64]) -> i64 {
     let permutations = get_permutations(0);
 
     let mut output = 0;
@@ -41,19 +49,19 @@ fn part_1(memory: &Vec<i64>) {
 
         tx_0.send(0).expect("Cannot feed first input");
 
-        let mut c_0 = computer::new(memory.len(), memory);
+        let mut c_0 = computer::new(Level::Day5, memory.len(), memory);
         c_0.set_reader(Box::new(reader::channel(rx_0)));
         c_0.register_writer(Box::new(writer::channel(tx_1)));
-        let mut c_1 = computer::new(memory.len(), memory);
+        let mut c_1 = computer::new(Level::Day5, memory.len(), memory);
         c_1.set_reader(Box::new(reader::channel(rx_1)));
         c_1.register_writer(Box::new(writer::channel(tx_2)));
-        let mut c_2 = computer::new(memory.len(), memory);
+        let mut c_2 = computer::new(Level::Day5, memory.len(), memory);
         c_2.set_reader(Box::new(reader::channel(rx_2)));
         c_2.register_writer(Box::new(writer::channel(tx_3)));
-        let mut c_3 = computer::new(memory.len(), memory);
+        let mut c_3 = computer::new(Level::Day5, memory.len(), memory);
         c_3.set_reader(Box::new(reader::channel(rx_3)));
         c_3.register_writer(Box::new(writer::channel(tx_4)));
-        let mut c_4 = computer::new(memory.len(), memory);
+        let mut c_4 = computer::new(Level::Day5, memory.len(), memory);
         c_4.set_reader(Box::new(reader::channel(rx_4)));
         c_4.register_writer(Box::new(writer::channel(tx_ans)));
 
@@ -74,84 +82,43 @@ fn part_1(memory: &Vec<i64>) {
         }
     }
 
-    println!("Part 1: {}", output);
+    return output;
 }
 
-fn part_2(memory: &Vec<i64>) {
+fn part_2(memory: &[i64]) -> i64 {
     let permutations = get_permutations(5);
 
     let mut output = 0;
     for inputs in permutations {
-        let (tx_0, rx_0) = channel();
-        let (tx_1, rx_1) = channel();
-        let (tx_2, rx_2) = channel();
-        let (tx_3, rx_3) = channel();
-        let (tx_4, rx_4) = channel();
-        let (tx_ans, rx_ans) = channel();
-
-        tx_0.send(inputs[0]).expect("Cannot init tx_0");
-        tx_1.send(inputs[1]).expect("Cannot init tx_1");
-        tx_2.send(inputs[2]).expect("Cannot init tx_2");
-        tx_3.send(inputs[3]).expect("Cannot init tx_3");
-        tx_4.send(inputs[4]).expect("Cannot init tx_4");
-
-        tx_0.send(0).expect("Cannot feed first input");
-
-        let m_0 = memory.clone();
-        let t_0 = thread::Builder::new().name("0".to_string()).spawn(move || {
-            let mut c_0 = computer::new(m_0.len(), &m_0);
-            c_0.set_reader(Box::new(reader::channel(rx_0)));
-            c_0.register_writer(Box::new(writer::channel(tx_1)));
-            c_0.execute();
-        });
-        let m_1 = memory.clone();
-        let t_1 = thread::Builder::new().name("1".to_string()).spawn(move || {
-            let mut c_1 = computer::new(m_1.len(), &m_1);
-            c_1.set_reader(Box::new(reader::channel(rx_1)));
-            c_1.register_writer(Box::new(writer::channel(tx_2)));
-            c_1.execute();
-        });
-        let m_2 = memory.clone();
-        let t_2 = thread::Builder::new().name("2".to_string()).spawn(move || {
-            let mut c_2 = computer::new(m_2.len(), &m_2);
-            c_2.set_reader(Box::new(reader::channel(rx_2)));
-            c_2.register_writer(Box::new(writer::channel(tx_3)));
-            c_2.execute();
-        });
-        let m_3 = memory.clone();
-        let t_3 = thread::Builder::new().name("3".to_string()).spawn(move || {
-            let mut c_3 = computer::new(m_3.len(), &m_3);
-            c_3.set_reader(Box::new(reader::channel(rx_3)));
-            c_3.register_writer(Box::new(writer::channel(tx_4)));
-            c_3.execute();
-        });
-        let m_4 = memory.clone();
-        let t_4 = thread::Builder::new().name("4".to_string()).spawn(move || {
-            let mut c_4 = computer::new(m_4.len(), &m_4);
-            c_4.set_reader(Box::new(reader::channel(rx_4)));
-            c_4.register_writer(Box::new(writer::channel(tx_0)));
-            c_4.register_writer(Box::new(writer::channel(tx_ans)));
-            c_4.execute();
-        });
-
-        t_0.unwrap().join().expect("t_0");
-        t_1.unwrap().join().expect("t_0");
-        t_2.unwrap().join().expect("t_0");
-        t_3.unwrap().join().expect("t_0");
-        t_4.unwrap().join().expect("t_0");
-
-        loop {
-            let o = rx_ans.recv_timeout(Duration::from_millis(10)).unwrap_or(-1);
-            if o == -1 {
-                break;
+        let mut executor = asynchronous::executor();
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..5).map(|_| asynchronous::channel()).unzip();
+        for (sender, phase) in senders.iter().zip(&inputs) {
+            sender.send(*phase);
+        }
+        senders[0].send(0);
+
+        // The last amplifier feeds the first one and the answer.
+        let (tx_ans, rx_ans) = asynchronous::channel();
+        for (i, receiver) in receivers.into_iter().enumerate() {
+            let c = computer::new(Level::Day5, memory.len(), memory);
+            let mut sinks: Vec<Box<dyn Sink>> = vec![Box::new(senders[(i + 1) % 5].clone())];
+            if i == 4 {
+                sinks.push(Box::new(tx_ans.clone()));
             }
+            executor.spawn(asynchronous::execute(c, Box::new(receiver), sinks));
+        }
+        drop(senders);
+        assert_eq!(0, executor.run());
+
+        if let Some(o) = rx_ans.drain().pop() {
             if output < o {
                 output = o;
             }
         }
     }
 
-    println!("Part 2: {}", output);
+    return output;
 }
 
 fn get_permutations(offset: i64) -> Vec<Vec<i64>> {
@@ -1,89 +1,83 @@
-use std::fs;
+use crate::days::Solution;
+use crate::grid;
+use crate::grid::Grid;
+use crate::parse;
 
-const WIDTH: usize = 25;
-const HEIGHT: usize = 6;
-const LAYER_SIZE: usize = WIDTH * HEIGHT;
-
-pub fn run(input: &str) {
-    let pixels = load_inputs(input);
-    part_1(&pixels);
-    part_2(&pixels);
+/// Size of the image, which is smaller in the example.
+pub struct Day {
+    width: usize,
+    height: usize,
 }
 
-fn load_inputs(file_name: &str) -> Vec<u32> {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    return content.chars().map(|x| x.to_digit(10).unwrap()).collect();
+pub fn day(example: bool) -> Day {
+    if example {
+        return Day {
+            width: 2,
+            height: 2,
+        };
+    }
+    return Day {
+        width: 25,
+        height: 6,
+    };
 }
 
-fn part_1(pixels: &Vec<u32>) {
-    let layers = pixels.len() / LAYER_SIZE;
-    assert_eq!(pixels.len(), layers * LAYER_SIZE);
-    println!("Part 1 has {} layers", layers);
-    let mut layer_low_zeros: usize = std::usize::MAX;
-    let mut low_n_zeros: usize = std::usize::MAX;
-    for layer in 0..layers {
-        let mut n_zeros: usize = 0;
-        for i in layer * LAYER_SIZE..(layer + 1) * LAYER_SIZE {
-            if pixels[i] == 0 {
-                n_zeros += 1;
-            }
-        }
-        if n_zeros < low_n_zeros {
-            layer_low_zeros = layer;
-            low_n_zeros = n_zeros;
-        }
+impl Solution for Day {
+    type Input = Vec<Grid<u32>>;
+    type Answer1 = u32;
+    type Answer2 = String;
+
+    fn parse(&self, text: &str) -> parse::Result<Vec<Grid<u32>>> {
+        return load_inputs(text, self.width, self.height);
     }
-    println!("Lowest layer: {}", layer_low_zeros);
 
-    let mut n_ones = 0;
-    let mut n_twos = 0;
-    for i in layer_low_zeros * LAYER_SIZE..(layer_low_zeros + 1) * LAYER_SIZE {
-        if pixels[i] == 1 {
-            n_ones += 1;
-        }
-        if pixels[i] == 2 {
-            n_twos += 1;
-        }
+    fn part_1(&self, input: &Vec<Grid<u32>>) -> u32 {
+        return part_1(input);
+    }
+
+    fn part_2(&self, input: &Vec<Grid<u32>>) -> String {
+        return part_2(input);
     }
-    println!(
-        "Ones: {}, Twos: {}, ones*twos: {}",
-        n_ones,
-        n_twos,
-        n_ones * n_twos
-    );
 }
 
-fn part_2(pixels: &Vec<u32>) {
-    let mut image = [2; LAYER_SIZE];
+fn load_inputs(content: &str, width: usize, height: usize) -> parse::Result<Vec<Grid<u32>>> {
+    let pixels = parse::line(content, parse::digits)?;
+    let layer_size = width * height;
+    if pixels.is_empty() || pixels.len() % layer_size != 0 {
+        let message = format!("expected layers of {} pixels", layer_size);
+        return Err(parse::error(1, pixels.len() + 1, &message));
+    }
+    return Ok(pixels
+        .chunks(layer_size)
+        .map(|layer| grid::from_cells(width, height, layer.to_vec()))
+        .collect());
+}
 
-    let layers = pixels.len() / LAYER_SIZE;
-    assert_eq!(pixels.len(), layers * LAYER_SIZE);
+fn count(layer: &Grid<u32>, pixel: u32) -> u32 {
+    return layer.iter().filter(|(_, p)| **p == pixel).count() as u32;
+}
 
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            let i = y * WIDTH + x;
+fn part_1(layers: &[Grid<u32>]) -> u32 {
+    let layer = layers
+        .iter()
+        .min_by_key(|layer| count(layer, 0))
+        .expect("No layers");
+    return count(layer, 1) * count(layer, 2);
+}
 
-            for layer in 0..layers {
-                let j = layer * LAYER_SIZE + i;
-                if pixels[j] == 2 {
-                    continue;
-                }
-                image[i] = pixels[j];
-                break;
-            }
-        }
+fn part_2(layers: &[Grid<u32>]) -> String {
+    let mut image = grid::grid(layers[0].width(), layers[0].height(), 2);
+    for point in image.points().collect::<Vec<_>>() {
+        image[point] = layers
+            .iter()
+            .map(|layer| layer[point])
+            .find(|p| *p != 2)
+            .unwrap_or(2);
     }
 
-    assert_eq!(image.len(), HEIGHT * WIDTH);
-
-    for y in 0..HEIGHT {
-        for x in 0..WIDTH {
-            match image[y * WIDTH + x] {
-                0 => print!("▉"),
-                1 => print!(" "),
-                _ => panic!("Not a giffy"),
-            }
-        }
-        println!("");
-    }
+    return image.render(|pixel| match pixel {
+        0 => '▉',
+        1 => ' ',
+        _ => panic!("Not a giffy"),
+    });
 }
@@ -1,33 +1,49 @@
 use crate::computer;
-use crate::computer::Computer;
+use crate::computer::{Computer, Level};
+use crate::days::Solution;
+use crate::parse;
 use crate::reader;
 use crate::writer;
-use std::fs;
+use std::cell::RefCell;
+use std::rc::Rc;
 
-pub fn run(input: &str) {
-    let inputs = load_inputs(input);
-    part_1(&inputs);
-    part_2(&inputs);
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = Vec<i64>;
+    type Answer1 = i64;
+    type Answer2 = i64;
+
+    fn parse(&self, text: &str) -> parse::Result<Vec<i64>> {
+        return parse::program(text);
+    }
+
+    fn part_1(&self, input: &Vec<i64>) -> i64 {
+        return part_1(input);
+    }
+
+    fn part_2(&self, input: &Vec<i64>) -> i64 {
+        return part_2(input);
+    }
 }
 
-fn load_inputs(file_name: &str) -> Vec<i64> {
-    let content = fs::read_to_string(file_name).expect("Could not read file");
-    return content
-        .split(",")
-        .map(|x| x.parse::<i64>().unwrap())
-        .collect();
+fn part_1(memory: &[i64]) -> i64 {
+    return boost(memory, 1);
 }
 
-fn part_1(memory: &Vec<i64>) {
-    let mut c = computer::new(100000, memory);
-    c.set_reader(Box::new(reader::once(1)));
-    c.register_writer(Box::new(writer::StdOut {}));
-    assert_eq!(0, c.execute());
+fn part_2(memory: &[i64]) -> i64 {
+    return boost(memory, 2);
 }
 
-fn part_2(memory: &Vec<i64>) {
-    let mut c = computer::new(100000, memory);
-    c.set_reader(Box::new(reader::once(2)));
-    c.register_writer(Box::new(writer::StdOut {}));
+/// Runs BOOST in the given mode. Anything but a single output lists the
+/// opcodes that malfunction.
+fn boost(memory: &[i64], mode: i64) -> i64 {
+    let outputs = Rc::new(RefCell::new(Vec::new()));
+    let mut c = computer::new(Level::Day9, 100000, memory);
+    c.set_reader(Box::new(reader::once(mode)));
+    c.register_writer(Box::new(writer::buffer(outputs.clone())));
     assert_eq!(0, c.execute());
+    let outputs = outputs.borrow();
+    assert_eq!(outputs.len(), 1, "Malfunctioning opcodes: {:?}", outputs);
+    return outputs[0];
 }
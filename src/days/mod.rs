@@ -1,4 +1,5 @@
 pub mod day_1;
+pub mod day_10;
 pub mod day_2;
 pub mod day_3;
 pub mod day_4;
@@ -7,4 +8,155 @@ pub mod day_6;
 pub mod day_7;
 pub mod day_8;
 pub mod day_9;
-pub mod day_10;
+
+use crate::allocator;
+use crate::parse;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+
+/// A day of the calendar: how to read its input and solve both parts.
+pub trait Solution {
+    type Input;
+    type Answer1: Display;
+    type Answer2: Display;
+
+    fn parse(&self, text: &str) -> parse::Result<Self::Input>;
+    fn part_1(&self, input: &Self::Input) -> Self::Answer1;
+    fn part_2(&self, input: &Self::Input) -> Self::Answer2;
+}
+
+/// A solution with its types erased, so days can be kept together.
+pub trait Puzzle {
+    /// Parses the input and answers both parts, measuring each step.
+    fn solve(&self, text: &str) -> Result<Solved, String>;
+    /// Only parses the input.
+    fn check(&self, text: &str) -> Result<(), String>;
+}
+
+impl<S: Solution> Puzzle for S {
+    fn check(&self, text: &str) -> Result<(), String> {
+        self.parse(text)?;
+        return Ok(());
+    }
+
+    fn solve(&self, text: &str) -> Result<Solved, String> {
+        let (input, parse) = measure(|| self.parse(text));
+        let input = input?;
+        return Ok(Solved {
+            parse: parse,
+            answers: vec![
+                answer(|| self.part_1(&input)),
+                answer(|| self.part_2(&input)),
+            ],
+        });
+    }
+}
+
+/// Every solved day by its number, set up for the example inputs with
+/// `example`.
+pub fn registry(example: bool) -> BTreeMap<u8, Box<dyn Puzzle>> {
+    let mut days: BTreeMap<u8, Box<dyn Puzzle>> = BTreeMap::new();
+    days.insert(1, Box::new(day_1::Day {}));
+    days.insert(2, Box::new(day_2::Day {}));
+    days.insert(3, Box::new(day_3::Day {}));
+    days.insert(4, Box::new(day_4::Day {}));
+    days.insert(5, Box::new(day_5::Day {}));
+    days.insert(6, Box::new(day_6::Day {}));
+    days.insert(7, Box::new(day_7::Day {}));
+    days.insert(8, Box::new(day_8::day(example)));
+    days.insert(9, Box::new(day_9::Day {}));
+    days.insert(10, Box::new(day_10::Day {}));
+    return days;
+}
+
+/// Runs a step, turning a panic into its message.
+pub fn catch<T>(step: impl FnOnce() -> T) -> Result<T, String> {
+    return panic::catch_unwind(AssertUnwindSafe(step)).map_err(|e| {
+        match (e.downcast_ref::<String>(), e.downcast_ref::<&str>()) {
+            (Some(message), _) => message.clone(),
+            (_, Some(message)) => String::from(*message),
+            _ => String::from("Panicked"),
+        }
+    });
+}
+
+/// What a step of solving a day took: time and the most memory allocated at
+/// once on top of what was allocated before it. Allocations are only counted
+/// with `allocator::Counting` as the global allocator.
+#[derive(Debug, Clone, Copy)]
+pub struct Cost {
+    pub elapsed: Duration,
+    pub peak_bytes: usize,
+}
+
+pub fn measure<T>(step: impl FnOnce() -> T) -> (T, Cost) {
+    let before = allocator::reset_peak();
+    let start = Instant::now();
+    let result = step();
+    let elapsed = start.elapsed();
+    return (
+        result,
+        Cost {
+            elapsed: elapsed,
+            peak_bytes: allocator::peak().saturating_sub(before),
+        },
+    );
+}
+
+/// Answer of one part of a day and what computing it took.
+pub struct Answer {
+    pub answer: String,
+    pub cost: Cost,
+}
+
+pub fn answer<T: ToString>(part: impl FnOnce() -> T) -> Answer {
+    let (answer, cost) = measure(|| part().to_string());
+    return Answer {
+        answer: answer,
+        cost: cost,
+    };
+}
+
+pub struct Solved {
+    pub parse: Cost,
+    pub answers: Vec<Answer>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::answers;
+    use std::fs;
+
+    fn solve(day: u8, input: &str) -> Vec<String> {
+        let text = fs::read_to_string(input).unwrap();
+        return registry(input.ends_with("_test.txt"))[&day]
+            .solve(&text)
+            .unwrap()
+            .answers
+            .into_iter()
+            .map(|a| a.answer)
+            .collect();
+    }
+
+    #[test]
+    fn test_examples() {
+        assert_eq!(solve(6, "inputs/day_6_test.txt"), ["54", "4"]);
+        assert_eq!(solve(8, "inputs/day_8_test.txt"), ["4", "▉ \n ▉\n"]);
+        assert_eq!(solve(10, "inputs/day_10_test.txt"), ["210", "802"]);
+    }
+
+    #[test]
+    fn test_answers() {
+        let answers = answers::load("answers.toml").unwrap();
+        for day in registry(false).keys() {
+            let solved = solve(*day, &format!("inputs/day_{}.txt", day));
+            for (part, answer) in solved.iter().enumerate() {
+                assert_eq!(answer, &answers[&(*day, part as u8 + 1)], "Day {}", day);
+            }
+        }
+    }
+}
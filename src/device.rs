@@ -0,0 +1,160 @@
+use crate::xorshift;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Something mapped over a range of machine memory. Operand reads and writes
+/// in the range go to the device instead of memory; instruction fetches do
+/// not.
+pub trait Device {
+    /// Number of cells the device occupies, read once when it is mapped.
+    fn size(&self) -> usize;
+    fn read(&mut self, offset: usize) -> i64;
+    fn write(&mut self, offset: usize, value: i64);
+    /// State that `restore` puts back when the machine is restored.
+    fn snapshot(&self) -> Vec<i64>;
+    fn restore(&mut self, state: &[i64]);
+}
+
+/// Milliseconds since the clock was created. Writes are ignored. A restored
+/// clock goes on from the time of its snapshot.
+pub struct Clock {
+    start: Instant,
+}
+
+impl Device for Clock {
+    fn size(&self) -> usize {
+        return 1;
+    }
+
+    fn read(&mut self, _: usize) -> i64 {
+        return self.start.elapsed().as_millis() as i64;
+    }
+
+    fn write(&mut self, _: usize, _: i64) {}
+
+    fn snapshot(&self) -> Vec<i64> {
+        return vec![self.start.elapsed().as_millis() as i64];
+    }
+
+    fn restore(&mut self, state: &[i64]) {
+        let elapsed = Duration::from_millis(state[0] as u64);
+        let now = Instant::now();
+        self.start = now.checked_sub(elapsed).unwrap_or(now);
+    }
+}
+
+pub fn clock() -> Clock {
+    return Clock {
+        start: Instant::now(),
+    };
+}
+
+/// Non-negative pseudo-random numbers. Writing a value reseeds the generator.
+pub struct Random {
+    random: xorshift::Random,
+}
+
+impl Device for Random {
+    fn size(&self) -> usize {
+        return 1;
+    }
+
+    fn read(&mut self, _: usize) -> i64 {
+        return (self.random.next_u64() >> 1) as i64;
+    }
+
+    fn write(&mut self, _: usize, value: i64) {
+        self.random = xorshift::random(value as u64);
+    }
+
+    fn snapshot(&self) -> Vec<i64> {
+        return vec![self.random.state() as i64];
+    }
+
+    fn restore(&mut self, state: &[i64]) {
+        self.random = xorshift::random(state[0] as u64);
+    }
+}
+
+pub fn random(seed: u64) -> Random {
+    return Random {
+        random: xorshift::random(seed),
+    };
+}
+
+/// Pixels stored row by row in a buffer shared with the host, which can render
+/// it while or after the machine runs. The device keeps the size the buffer
+/// had when it was mapped: pixels the host removes since then read as 0 and
+/// ignore writes.
+pub struct Framebuffer {
+    pixels: Rc<RefCell<Vec<i64>>>,
+}
+
+impl Device for Framebuffer {
+    fn size(&self) -> usize {
+        return self.pixels.borrow().len();
+    }
+
+    fn read(&mut self, offset: usize) -> i64 {
+        return self.pixels.borrow().get(offset).cloned().unwrap_or(0);
+    }
+
+    fn write(&mut self, offset: usize, value: i64) {
+        if let Some(pixel) = self.pixels.borrow_mut().get_mut(offset) {
+            *pixel = value;
+        }
+    }
+
+    fn snapshot(&self) -> Vec<i64> {
+        return self.pixels.borrow().clone();
+    }
+
+    fn restore(&mut self, state: &[i64]) {
+        *self.pixels.borrow_mut() = state.to_vec();
+    }
+}
+
+pub fn framebuffer(pixels: Rc<RefCell<Vec<i64>>>) -> Framebuffer {
+    return Framebuffer { pixels: pixels };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_reseed() {
+        let mut device = random(7);
+        let first: Vec<i64> = (0..3).map(|_| device.read(0)).collect();
+        assert!(first.iter().all(|v| *v >= 0));
+        assert_ne!(first[0], first[1]);
+        device.write(0, 7);
+        let second: Vec<i64> = (0..3).map(|_| device.read(0)).collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_framebuffer_shared() {
+        let pixels = Rc::new(RefCell::new(vec![0; 6]));
+        let mut device = framebuffer(pixels.clone());
+        assert_eq!(device.size(), 6);
+        device.write(4, 1);
+        assert_eq!(device.read(4), 1);
+        assert_eq!(*pixels.borrow(), vec![0, 0, 0, 0, 1, 0]);
+
+        pixels.borrow_mut().truncate(2);
+        device.write(4, 2);
+        assert_eq!(device.read(4), 0);
+        assert_eq!(*pixels.borrow(), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_random_restore() {
+        let mut device = random(7);
+        let state = device.snapshot();
+        let first = device.read(0);
+        device.restore(&state);
+        assert_eq!(device.read(0), first);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::computer::{
+    parameter_count, Level, INSTRUCTION_ADD, INSTRUCTION_EQUALS, INSTRUCTION_LESS_THAN,
+    INSTRUCTION_MUL, INSTRUCTION_READ,
+};
+use crate::disassembler::mnemonic;
+use std::collections::BTreeMap;
+
+/// How a custom opcode uses one of its parameters. Read parameters accept
+/// every mode, write parameters every mode but immediate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parameter {
+    Read,
+    Write,
+}
+
+/// A custom opcode with the mnemonic the disassembler and tracer show for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opcode {
+    pub opcode: i64,
+    pub mnemonic: &'static str,
+    pub parameters: Vec<Parameter>,
+}
+
+/// The opcodes a machine understands: the built-in ones of its level plus
+/// custom ones.
+#[derive(Debug, Clone)]
+pub struct Dialect {
+    level: Level,
+    custom: BTreeMap<i64, Opcode>,
+}
+
+pub fn new(level: Level) -> Dialect {
+    return Dialect {
+        level: level,
+        custom: BTreeMap::new(),
+    };
+}
+
+/// Every built-in instruction and mode.
+impl Default for Dialect {
+    fn default() -> Dialect {
+        return new(Level::Day9);
+    }
+}
+
+impl Dialect {
+    /// Adds a custom opcode. It must fit in two digits, not clash with an
+    /// existing opcode or mnemonic, and take at most three parameters of which
+    /// at most one is written.
+    pub fn add(&mut self, opcode: Opcode) {
+        assert!(
+            0 < opcode.opcode && opcode.opcode < 100,
+            "Opcode out of range"
+        );
+        assert!(
+            self.parameter_count(opcode.opcode).is_none(),
+            "Opcode {} is already defined",
+            opcode.opcode
+        );
+        assert!(
+            self.opcode(opcode.mnemonic).is_none(),
+            "Mnemonic {} is already defined",
+            opcode.mnemonic
+        );
+        assert!(opcode.parameters.len() <= 3, "Too many parameters");
+        assert!(
+            opcode
+                .parameters
+                .iter()
+                .filter(|p| **p == Parameter::Write)
+                .count()
+                <= 1,
+            "More than one write parameter"
+        );
+        self.custom.insert(opcode.opcode, opcode);
+    }
+
+    pub fn get_custom(&self, opcode: i64) -> Option<&Opcode> {
+        return self.custom.get(&opcode);
+    }
+
+    pub fn parameter_count(&self, opcode: i64) -> Option<usize> {
+        if let Some(custom) = self.custom.get(&opcode) {
+            return Some(custom.parameters.len());
+        }
+        if !self.level.has_opcode(opcode) {
+            return None;
+        }
+        return parameter_count(opcode);
+    }
+
+    pub fn has_mode(&self, mode: i64) -> bool {
+        return self.level.has_mode(mode);
+    }
+
+    /// Index of the parameter the opcode writes its result to, if any.
+    pub fn output_parameter(&self, opcode: i64) -> Option<usize> {
+        if let Some(custom) = self.custom.get(&opcode) {
+            return custom
+                .parameters
+                .iter()
+                .position(|p| *p == Parameter::Write);
+        }
+        return match opcode {
+            INSTRUCTION_ADD | INSTRUCTION_MUL | INSTRUCTION_LESS_THAN | INSTRUCTION_EQUALS => {
+                Some(2)
+            }
+            INSTRUCTION_READ => Some(0),
+            _ => None,
+        };
+    }
+
+    pub fn mnemonic(&self, opcode: i64) -> &'static str {
+        if let Some(custom) = self.custom.get(&opcode) {
+            return custom.mnemonic;
+        }
+        return mnemonic(opcode);
+    }
+
+    /// Opcode with the given mnemonic.
+    pub fn opcode(&self, mnemonic: &str) -> Option<i64> {
+        return (1..100)
+            .find(|o| self.parameter_count(*o).is_some() && self.mnemonic(*o) == mnemonic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::INSTRUCTION_JUMP_IF_TRUE;
+
+    fn swap() -> Opcode {
+        return Opcode {
+            opcode: 20,
+            mnemonic: "swp",
+            parameters: vec![Parameter::Read, Parameter::Write],
+        };
+    }
+
+    #[test]
+    fn test_builtin() {
+        let dialect = Dialect::default();
+        assert_eq!(dialect.output_parameter(INSTRUCTION_ADD), Some(2));
+        assert_eq!(dialect.output_parameter(INSTRUCTION_READ), Some(0));
+        assert_eq!(dialect.output_parameter(INSTRUCTION_JUMP_IF_TRUE), None);
+        assert_eq!(dialect.opcode("jnz"), Some(INSTRUCTION_JUMP_IF_TRUE));
+        assert_eq!(dialect.opcode("swp"), None);
+    }
+
+    #[test]
+    fn test_custom() {
+        let mut dialect = Dialect::default();
+        dialect.add(swap());
+        assert_eq!(dialect.parameter_count(20), Some(2));
+        assert_eq!(dialect.output_parameter(20), Some(1));
+        assert_eq!(dialect.mnemonic(20), "swp");
+        assert_eq!(dialect.opcode("swp"), Some(20));
+    }
+
+    #[test]
+    #[should_panic(expected = "Opcode 1 is already defined")]
+    fn test_builtin_clash() {
+        let mut dialect = Dialect::default();
+        dialect.add(Opcode {
+            opcode: 1,
+            ..swap()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Mnemonic swp is already defined")]
+    fn test_mnemonic_clash() {
+        let mut dialect = Dialect::default();
+        dialect.add(swap());
+        dialect.add(Opcode {
+            opcode: 21,
+            ..swap()
+        });
+    }
+
+    #[test]
+    fn test_level() {
+        let dialect = new(Level::Day5);
+        assert_eq!(dialect.parameter_count(INSTRUCTION_JUMP_IF_TRUE), Some(2));
+        assert_eq!(dialect.opcode("arb"), None);
+        assert!(dialect.has_mode(1));
+        assert!(!dialect.has_mode(2));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::computer;
+use crate::computer::{Computer, Level};
+use crate::reader;
+use crate::tracer;
+use crate::writer;
+use std::cell::RefCell;
+use std::fmt;
+use std::panic;
+use std::rc::Rc;
+use std::sync::mpsc::channel;
+
+/// How many trace lines a divergence report shows per engine.
+const TRACE_TAIL: usize = 8;
+
+/// Observable state of a program after an engine ran it to completion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Run {
+    /// Exit code of `execute`, or the panic message if the engine panicked.
+    pub status: Result<i8, String>,
+    pub outputs: Vec<i64>,
+    pub memory: Vec<i64>,
+    pub instruction_count: u64,
+    /// Executed instructions, for engines that trace.
+    pub trace: Vec<String>,
+}
+
+pub trait Engine {
+    fn name(&self) -> &str;
+    fn run(&self, program: &[i64], inputs: &[i64]) -> Run;
+}
+
+/// Plain `IntComputer`, the engine every other one is compared against.
+pub struct Reference {
+    pub memory_size: usize,
+}
+
+/// `IntComputer` with a tracer attached.
+pub struct Traced {
+    pub memory_size: usize,
+}
+
+#[derive(Debug)]
+pub struct Divergence {
+    /// What differs first, such as `output 3` or `memory[12]`.
+    pub at: String,
+    pub engines: (String, String),
+    pub runs: (Run, Run),
+}
+
+fn run_computer(memory_size: usize, program: &[i64], inputs: &[i64], traced: bool) -> Run {
+    let (tx, rx) = channel();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut c = computer::new(Level::Day9, memory_size.max(program.len()), program);
+    c.set_reader(Box::new(reader::sequence(inputs)));
+    c.register_writer(Box::new(writer::channel(tx)));
+    if traced {
+        c.set_tracer(Box::new(tracer::recorder(log.clone())));
+    }
+
+    let status = panic::catch_unwind(panic::AssertUnwindSafe(|| c.execute()));
+    let status = status.map_err(|e| {
+        if let Some(message) = e.downcast_ref::<&str>() {
+            String::from(*message)
+        } else if let Some(message) = e.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            String::from("panic")
+        }
+    });
+
+    let trace = log.borrow().clone();
+    return Run {
+        status: status,
+        outputs: rx.try_iter().collect(),
+        memory: c.get_memory().to_vec(),
+        instruction_count: c.get_instruction_count(),
+        trace: trace,
+    };
+}
+
+impl Engine for Reference {
+    fn name(&self) -> &str {
+        return "reference";
+    }
+
+    fn run(&self, program: &[i64], inputs: &[i64]) -> Run {
+        return run_computer(self.memory_size, program, inputs, false);
+    }
+}
+
+impl Engine for Traced {
+    fn name(&self) -> &str {
+        return "traced";
+    }
+
+    fn run(&self, program: &[i64], inputs: &[i64]) -> Run {
+        return run_computer(self.memory_size, program, inputs, true);
+    }
+}
+
+fn first_difference(left: &[i64], right: &[i64]) -> Option<usize> {
+    if let Some(i) = left.iter().zip(right).position(|(l, r)| l != r) {
+        return Some(i);
+    }
+    if left.len() != right.len() {
+        return Some(left.len().min(right.len()));
+    }
+    return None;
+}
+
+fn divergence(left: &Run, right: &Run) -> Option<String> {
+    if left.status != right.status {
+        return Some(String::from("status"));
+    }
+    if let Some(i) = first_difference(&left.outputs, &right.outputs) {
+        return Some(format!("output {}", i));
+    }
+    if let Some(i) = first_difference(&left.memory, &right.memory) {
+        return Some(format!("memory[{}]", i));
+    }
+    if left.instruction_count != right.instruction_count {
+        return Some(String::from("instruction count"));
+    }
+    return None;
+}
+
+/// Runs the program on every engine and compares each against the first.
+/// Returns the reference run, or the first divergence found.
+pub fn compare(
+    engines: &[&dyn Engine],
+    program: &[i64],
+    inputs: &[i64],
+) -> Result<Run, Box<Divergence>> {
+    let reference = engines[0].run(program, inputs);
+    for engine in &engines[1..] {
+        let run = engine.run(program, inputs);
+        if let Some(at) = divergence(&reference, &run) {
+            return Err(Box::new(Divergence {
+                at: at,
+                engines: (String::from(engines[0].name()), String::from(engine.name())),
+                runs: (reference, run),
+            }));
+        }
+    }
+    return Ok(reference);
+}
+
+fn format_run(f: &mut fmt::Formatter, name: &str, run: &Run, at: &str) -> fmt::Result {
+    writeln!(f, "  {}:", name)?;
+    writeln!(f, "    status: {:?}", run.status)?;
+    writeln!(f, "    outputs: {:?}", run.outputs)?;
+    writeln!(f, "    instructions: {}", run.instruction_count)?;
+    if at.starts_with("memory[") {
+        let i: usize = at[7..at.len() - 1].parse().unwrap();
+        let from = i.saturating_sub(4);
+        let to = (i + 5).min(run.memory.len());
+        writeln!(
+            f,
+            "    memory[{}..{}]: {:?}",
+            from,
+            to,
+            &run.memory[from..to]
+        )?;
+    }
+    let skip = run.trace.len().saturating_sub(TRACE_TAIL);
+    for line in &run.trace[skip..] {
+        writeln!(f, "    {}", line)?;
+    }
+    return Ok(());
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} and {} diverge at {}",
+            self.engines.0, self.engines.1, self.at
+        )?;
+        format_run(f, &self.engines.0, &self.runs.0, &self.at)?;
+        format_run(f, &self.engines.1, &self.runs.1, &self.at)?;
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::Program;
+
+    /// Reference engine with an off-by-one in its outputs.
+    struct Broken {}
+
+    impl Engine for Broken {
+        fn name(&self) -> &str {
+            return "broken";
+        }
+
+        fn run(&self, program: &[i64], inputs: &[i64]) -> Run {
+            let mut run = run_computer(0, program, inputs, false);
+            if let Some(last) = run.outputs.last_mut() {
+                *last += 1;
+            }
+            return run;
+        }
+    }
+
+    #[test]
+    fn test_day_5() {
+        let program = computer::load_program("inputs/day_5.txt");
+        let run = compare(
+            &[&Reference { memory_size: 0 }, &Traced { memory_size: 0 }],
+            &program,
+            &[5],
+        )
+        .unwrap();
+        assert_eq!(run.status, Ok(0));
+        assert_eq!(run.outputs.len(), 1);
+    }
+
+    #[test]
+    fn test_report_divergence() {
+        let program = vec![4, 0, 104, 7, 99];
+        let divergence =
+            compare(&[&Traced { memory_size: 0 }, &Broken {}], &program, &[]).unwrap_err();
+        assert_eq!(divergence.at, "output 1");
+        let report = format!("{}", divergence);
+        assert!(report.starts_with("traced and broken diverge at output 1"));
+        assert!(report.contains("outputs: [4, 7]"));
+        assert!(report.contains("outputs: [4, 8]"));
+        assert!(report.contains("out 7"));
+    }
+
+    #[test]
+    fn test_status_divergence() {
+        let left = run_computer(0, &[104, 1, 99], &[], false);
+        let right = run_computer(0, &[104, 1, 42], &[], false);
+        assert_eq!(divergence(&left, &right), Some(String::from("status")));
+    }
+
+    #[quickcheck]
+    fn test_engines_agree(program: Program) -> bool {
+        let engines: [&dyn Engine; 2] = [&Reference { memory_size: 0 }, &Traced { memory_size: 0 }];
+        match compare(&engines, &program.code, &program.inputs) {
+            Ok(_) => true,
+            Err(divergence) => panic!("{}", divergence),
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use crate::computer::{
+    parse_instruction, INSTRUCTION_ADD, INSTRUCTION_EQUALS, INSTRUCTION_EXIT,
+    INSTRUCTION_JUMP_IF_FALSE, INSTRUCTION_JUMP_IF_TRUE, INSTRUCTION_LESS_THAN, INSTRUCTION_MUL,
+    INSTRUCTION_READ, INSTRUCTION_RELATIVE_ADD, INSTRUCTION_WRITE,
+};
+use crate::dialect::Dialect;
+use std::fmt;
+
+pub const MODE_POSITION: i64 = 0;
+pub const MODE_IMMEDIATE: i64 = 1;
+pub const MODE_RELATIVE: i64 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub address: usize,
+    pub opcode: i64,
+    pub modes: [i64; 3],
+    pub parameters: Vec<i64>,
+    pub mnemonic: &'static str,
+    /// Index of the parameter the instruction writes its result to, if any.
+    pub output: Option<usize>,
+}
+
+impl Instruction {
+    pub fn size(&self) -> usize {
+        return 1 + self.parameters.len();
+    }
+
+    pub fn next(&self) -> usize {
+        return self.address + self.size();
+    }
+}
+
+/// Decodes the instruction at `address`, or `None` when the cell does not hold
+/// a valid opcode, uses an unknown mode, writes in immediate mode or runs past
+/// the end of memory.
+pub fn decode(memory: &[i64], address: usize) -> Option<Instruction> {
+    return decode_with(&Dialect::default(), memory, address);
+}
+
+/// Like `decode`, also accepting the custom opcodes of `dialect`.
+pub fn decode_with(dialect: &Dialect, memory: &[i64], address: usize) -> Option<Instruction> {
+    let (opcode, am, bm, cm) = parse_instruction(*memory.get(address)?);
+    let count = dialect.parameter_count(opcode)?;
+    if address + count >= memory.len() {
+        return None;
+    }
+
+    // Like the machine, only the modes of parameters the opcode takes are
+    // checked.
+    let modes = [am, bm, cm];
+    if modes[..count].iter().any(|m| !dialect.has_mode(*m)) {
+        return None;
+    }
+
+    let instruction = Instruction {
+        address: address,
+        opcode: opcode,
+        modes: modes,
+        parameters: memory[address + 1..address + 1 + count].to_vec(),
+        mnemonic: dialect.mnemonic(opcode),
+        output: dialect.output_parameter(opcode),
+    };
+    if let Some(i) = instruction.output {
+        if instruction.modes[i] == MODE_IMMEDIATE {
+            return None;
+        }
+    }
+    return Some(instruction);
+}
+
+/// Cells of the instruction. Modes of parameters the opcode does not take are
+/// encoded as zero.
+pub fn encode(instruction: &Instruction) -> Vec<i64> {
+    let mut cell = instruction.opcode;
+    let mut factor = 100;
+    for mode in instruction.modes.iter().take(instruction.parameters.len()) {
+        cell += mode * factor;
+        factor *= 10;
+    }
+    let mut cells = vec![cell];
+    cells.extend(&instruction.parameters);
+    return cells;
+}
+
+/// One line per instruction, prefixed with its address. Cells that do not
+/// decode to an instruction that encodes back to the same value are listed as
+/// `data`.
+pub fn disassemble(memory: &[i64]) -> String {
+    return disassemble_with(&Dialect::default(), memory);
+}
+
+pub fn disassemble_with(dialect: &Dialect, memory: &[i64]) -> String {
+    let mut listing = String::new();
+    let mut address = 0;
+    while address < memory.len() {
+        match decode_with(dialect, memory, address) {
+            Some(instruction) if encode(&instruction)[0] == memory[address] => {
+                listing += &format!("{:>6}: {}\n", address, instruction);
+                address = instruction.next();
+            }
+            _ => {
+                listing += &format!("{:>6}: data {}\n", address, memory[address]);
+                address += 1;
+            }
+        }
+    }
+    return listing;
+}
+
+fn parse_parameter(text: &str) -> Option<(i64, i64)> {
+    if text.starts_with("[rb+") && text.ends_with(']') {
+        return Some((MODE_RELATIVE, text[4..text.len() - 1].parse().ok()?));
+    }
+    if text.starts_with("[rb-") && text.ends_with(']') {
+        return Some((MODE_RELATIVE, -text[4..text.len() - 1].parse::<i64>().ok()?));
+    }
+    if text.starts_with('[') && text.ends_with(']') {
+        return Some((MODE_POSITION, text[1..text.len() - 1].parse().ok()?));
+    }
+    return Some((MODE_IMMEDIATE, text.parse().ok()?));
+}
+
+/// Parses a listing in the format written by `disassemble`. Address prefixes
+/// are optional and ignored.
+pub fn assemble(source: &str) -> Result<Vec<i64>, String> {
+    return assemble_with(&Dialect::default(), source);
+}
+
+pub fn assemble_with(dialect: &Dialect, source: &str) -> Result<Vec<i64>, String> {
+    let mut memory = Vec::new();
+    for (n, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("Line {}: {}: {}", n + 1, message, line.trim());
+        let mut text = line.trim();
+        if let Some(i) = text.find(':') {
+            text = text[i + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (name, rest) = match text.find(' ') {
+            Some(i) => (&text[..i], text[i + 1..].trim()),
+            None => (text, ""),
+        };
+        let operands: Vec<&str> = if rest.is_empty() {
+            vec![]
+        } else {
+            rest.split(',').map(|o| o.trim()).collect()
+        };
+
+        if name == "data" {
+            if operands.len() != 1 {
+                return Err(error("data takes one value"));
+            }
+            memory.push(operands[0].parse().map_err(|_| error("invalid value"))?);
+            continue;
+        }
+
+        let opcode = dialect
+            .opcode(name)
+            .ok_or_else(|| error("unknown mnemonic"))?;
+        if dialect.parameter_count(opcode) != Some(operands.len()) {
+            return Err(error("wrong number of operands"));
+        }
+        let mut instruction = Instruction {
+            address: memory.len(),
+            opcode: opcode,
+            modes: [0; 3],
+            parameters: vec![],
+            mnemonic: dialect.mnemonic(opcode),
+            output: dialect.output_parameter(opcode),
+        };
+        for (i, operand) in operands.iter().enumerate() {
+            let (mode, value) = parse_parameter(operand).ok_or_else(|| error("invalid operand"))?;
+            instruction.modes[i] = mode;
+            instruction.parameters.push(value);
+        }
+        memory.extend(encode(&instruction));
+    }
+    return Ok(memory);
+}
+
+pub fn mnemonic(opcode: i64) -> &'static str {
+    match opcode {
+        INSTRUCTION_ADD => "add",
+        INSTRUCTION_MUL => "mul",
+        INSTRUCTION_READ => "in",
+        INSTRUCTION_WRITE => "out",
+        INSTRUCTION_JUMP_IF_TRUE => "jnz",
+        INSTRUCTION_JUMP_IF_FALSE => "jz",
+        INSTRUCTION_LESS_THAN => "lt",
+        INSTRUCTION_EQUALS => "eq",
+        INSTRUCTION_RELATIVE_ADD => "arb",
+        INSTRUCTION_EXIT => "hlt",
+        _ => "???",
+    }
+}
+
+fn format_parameter(mode: i64, parameter: i64) -> String {
+    match mode {
+        MODE_IMMEDIATE => format!("{}", parameter),
+        MODE_RELATIVE if parameter < 0 => format!("[rb-{}]", -parameter),
+        MODE_RELATIVE => format!("[rb+{}]", parameter),
+        _ => format!("[{}]", parameter),
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mnemonic)?;
+        for (i, parameter) in self.parameters.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(
+                f,
+                "{}{}",
+                separator,
+                format_parameter(self.modes[i], *parameter)
+            )?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer;
+    use crate::computer::{Computer, Level};
+    use crate::dialect::{Opcode, Parameter};
+    use crate::generator::Program;
+    use crate::writer;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn test_decode() {
+        let memory = vec![1002, 4, 3, 4, 33];
+        let instruction = decode(&memory, 0).unwrap();
+        assert_eq!(instruction.opcode, INSTRUCTION_MUL);
+        assert_eq!(instruction.modes, [0, 1, 0]);
+        assert_eq!(instruction.parameters, vec![4, 3, 4]);
+        assert_eq!(instruction.next(), 4);
+        assert_eq!(format!("{}", instruction), "mul [4], 3, [4]");
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(decode(&[42], 0), None);
+        assert_eq!(decode(&[1, 0, 0], 0), None);
+        assert_eq!(decode(&[10001, 0, 0, 0], 0), None);
+        assert_eq!(decode(&[301, 0, 0, 0], 0), None);
+    }
+
+    #[test]
+    fn test_decode_unused_modes() {
+        // hlt, jnz 1, 3 and out [0] with a mode of 3 where they take no
+        // parameter, all of which the machine runs.
+        for program in &[vec![30099], vec![31105, 1, 3, 99], vec![30004, 0, 99]] {
+            let (tx, _rx) = channel();
+            let mut computer = computer::new(Level::Day9, program.len(), program);
+            computer.register_writer(Box::new(writer::channel(tx)));
+            assert_eq!(computer.execute(), 0);
+            assert!(decode(program, 0).is_some());
+        }
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let memory = vec![1002, 4, 3, 4, 33, 109, -2, 204, 1, 99, 1100, 7];
+        let listing = disassemble(&memory);
+        assert_eq!(
+            listing,
+            concat!(
+                "     0: mul [4], 3, [4]\n",
+                "     4: data 33\n",
+                "     5: arb -2\n",
+                "     7: out [rb+1]\n",
+                "     9: hlt\n",
+                "    10: data 1100\n",
+                "    11: data 7\n",
+            )
+        );
+        assert_eq!(assemble(&listing), Ok(memory));
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("add 1, 2"),
+            Err(String::from("Line 1: wrong number of operands: add 1, 2"))
+        );
+        assert_eq!(
+            assemble("nop"),
+            Err(String::from("Line 1: unknown mnemonic: nop"))
+        );
+        assert_eq!(
+            assemble("\nout [x]"),
+            Err(String::from("Line 2: invalid operand: out [x]"))
+        );
+    }
+
+    #[quickcheck]
+    fn test_round_trip(program: Program) -> bool {
+        assemble(&disassemble(&program.code)) == Ok(program.code)
+    }
+
+    #[test]
+    fn test_format_relative() {
+        let instruction = decode(&[209, -3], 0).unwrap();
+        assert_eq!(format!("{}", instruction), "arb [rb-3]");
+    }
+
+    #[test]
+    fn test_custom_opcode() {
+        let mut dialect = Dialect::default();
+        dialect.add(Opcode {
+            opcode: 42,
+            mnemonic: "sleep",
+            parameters: vec![Parameter::Read],
+        });
+        let memory = vec![142, 10, 99];
+        assert_eq!(decode(&memory, 0), None);
+        let listing = disassemble_with(&dialect, &memory);
+        assert_eq!(listing, "     0: sleep 10\n     2: hlt\n");
+        assert_eq!(assemble_with(&dialect, &listing), Ok(memory));
+        assert!(assemble(&listing).is_err());
+    }
+}
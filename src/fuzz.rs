@@ -0,0 +1,151 @@
+use crate::computer;
+use crate::computer::{Computer, Level, Limits, Outcome};
+use crate::reader::Reader;
+use crate::xorshift::Random;
+use std::collections::VecDeque;
+
+/// Instructions a single run may execute before it is cut off.
+pub const INSTRUCTION_BUDGET: u64 = 100_000;
+/// Memory given to a program on top of its own length.
+pub const EXTRA_MEMORY: usize = 1024;
+
+/// Replays the fuzzed inputs, then reads zeros.
+struct Inputs {
+    values: VecDeque<i64>,
+}
+
+impl Reader for Inputs {
+    fn read(&mut self) -> i64 {
+        return self.values.pop_front().unwrap_or(0);
+    }
+}
+
+/// Runs an arbitrary program, discarding its outputs, for at most
+/// `INSTRUCTION_BUDGET` instructions. Any panic inside is a bug in the
+/// interpreter: every way a program can go wrong must end up as a fault.
+pub fn run(program: &[i64], inputs: &[i64]) -> Outcome {
+    let mut c = computer::new(Level::Day9, program.len() + EXTRA_MEMORY, program);
+    c.set_reader(Box::new(Inputs {
+        values: inputs.iter().cloned().collect(),
+    }));
+    return c.execute_with_limits(&Limits {
+        max_instructions: Some(INSTRUCTION_BUDGET),
+        ..Default::default()
+    });
+}
+
+/// Entry point for byte-oriented fuzzers. The data is read as little-endian
+/// `i64` words after a leading byte that says how many of the last words are
+/// inputs rather than program.
+pub fn run_bytes(data: &[u8]) -> Outcome {
+    if data.is_empty() {
+        return run(&[], &[]);
+    }
+    let words: Vec<i64> = data[1..]
+        .chunks(8)
+        .map(|chunk| {
+            let mut bytes = [0; 8];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            i64::from_le_bytes(bytes)
+        })
+        .collect();
+    let split = words.len() - (data[0] as usize).min(words.len());
+    return run(&words[..split], &words[split..]);
+}
+
+/// A program of mostly plausible cells: instructions with random modes,
+/// addresses near the program and the occasional extreme value.
+pub fn random_program(random: &mut Random, length: usize) -> Vec<i64> {
+    let opcodes = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+    return (0..length)
+        .map(|_| match random.below(10) {
+            0..=3 => {
+                let opcode = opcodes[random.below(opcodes.len() as u64) as usize];
+                // Mostly valid modes, sometimes an invalid 3.
+                opcode + 100 * random.below(4) + 1000 * random.below(4) + 10000 * random.below(3)
+            }
+            4..=8 => random.below(length as u64 + 20) - 10,
+            _ => random.next_u64() as i64,
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::{Fault, Limit};
+    use crate::xorshift;
+
+    #[test]
+    fn test_halts() {
+        assert_eq!(run(&[3, 0, 4, 0, 99], &[7]), Outcome::Halted);
+    }
+
+    #[test]
+    fn test_crashes_are_faults() {
+        assert_eq!(
+            run(&[1, -1, 0, 0, 99], &[]),
+            Outcome::Fault(Fault::InvalidAddress(0, -1))
+        );
+        assert_eq!(
+            run(&[1105, 1, 1_000_000], &[]),
+            Outcome::Fault(Fault::InvalidAddress(0, 1_000_000))
+        );
+        assert_eq!(
+            run(&[109, -5, 204, 0, 99], &[]),
+            Outcome::Fault(Fault::InvalidAddress(2, -5))
+        );
+        assert_eq!(
+            run(&[1102, i64::MAX, 2, 0, 99], &[]),
+            Outcome::Fault(Fault::Overflow(0))
+        );
+        assert_eq!(
+            run(&[11101, 1, 1, 0], &[]),
+            Outcome::Fault(Fault::ImmediateWrite(0))
+        );
+        assert_eq!(
+            run(&[301, 0, 0, 0], &[]),
+            Outcome::Fault(Fault::InvalidMode(0))
+        );
+    }
+
+    #[test]
+    fn test_jump_past_memory() {
+        let end = 3 + EXTRA_MEMORY as i64;
+        assert_eq!(
+            run(&[1106, 0, end], &[]),
+            Outcome::Fault(Fault::InvalidAddress(0, end))
+        );
+    }
+
+    #[test]
+    fn test_budget() {
+        assert_eq!(
+            run(&[1105, 1, 0], &[]),
+            Outcome::LimitExceeded(Limit::Instructions)
+        );
+    }
+
+    #[test]
+    fn test_random_programs() {
+        let mut random = xorshift::random(2019);
+        for _ in 0..2000 {
+            let length = random.below(64) as usize + 1;
+            let program = random_program(&mut random, length);
+            let inputs = random_program(&mut random, 4);
+            run(&program, &inputs);
+        }
+    }
+
+    #[quickcheck]
+    fn test_arbitrary_programs(program: Vec<i64>, inputs: Vec<i64>) -> bool {
+        run(&program, &inputs);
+        true
+    }
+
+    #[quickcheck]
+    fn test_arbitrary_bytes(data: Vec<u8>) -> bool {
+        run_bytes(&data);
+        true
+    }
+}
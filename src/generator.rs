@@ -0,0 +1,354 @@
+use crate::computer::{
+    INSTRUCTION_ADD, INSTRUCTION_EQUALS, INSTRUCTION_EXIT, INSTRUCTION_JUMP_IF_FALSE,
+    INSTRUCTION_JUMP_IF_TRUE, INSTRUCTION_LESS_THAN, INSTRUCTION_MUL, INSTRUCTION_READ,
+    INSTRUCTION_RELATIVE_ADD, INSTRUCTION_WRITE,
+};
+use crate::disassembler::{MODE_IMMEDIATE, MODE_POSITION, MODE_RELATIVE};
+use quickcheck::{Arbitrary, Gen};
+
+/// Scratch cells the program reads and writes.
+const SCRATCH_SIZE: usize = 8;
+/// Upper bound on executed instructions, which also keeps values far from
+/// overflowing: every instruction at most doubles the largest magnitude.
+const MAX_STEPS: usize = 40;
+const MAX_LOOP_ITERATIONS: usize = 3;
+
+/// A program that always halts, together with enough inputs for every read.
+///
+/// The layout is a prologue setting the relative base to the data area, the
+/// generated code, a halt, and the data area: scratch cells, then constants
+/// that are only ever read, then one counter per loop. Every opcode and every
+/// mode is used. Jumps only go forward except for the back edge of a loop,
+/// whose counter nothing else writes.
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub code: Vec<i64>,
+    pub inputs: Vec<i64>,
+}
+
+/// A cell whose value is only known once the layout is final.
+#[derive(Clone, Copy)]
+enum Cell {
+    Value(i64),
+    /// Address of a label.
+    Label(usize),
+    /// Absolute address of a data cell.
+    Data(usize),
+    /// Absolute address of a loop counter.
+    Counter(usize),
+    /// Offset of a data cell from a relative base shifted by the given amount
+    /// from the start of the data area.
+    Relative(usize, i64),
+}
+
+struct Builder {
+    code: Vec<Cell>,
+    /// Data cells after the scratch area, as values or labels.
+    constants: Vec<Cell>,
+    counters: usize,
+    labels: Vec<Option<usize>>,
+    inputs: Vec<i64>,
+    steps: usize,
+    /// Current relative base minus the start of the data area.
+    shift: i64,
+}
+
+fn below<G: Gen>(g: &mut G, n: usize) -> usize {
+    return usize::arbitrary(g) % n;
+}
+
+fn small<G: Gen>(g: &mut G) -> i64 {
+    return below(g, 41) as i64 - 20;
+}
+
+impl Builder {
+    fn label(&mut self) -> usize {
+        self.labels.push(None);
+        return self.labels.len() - 1;
+    }
+
+    fn place(&mut self, label: usize) {
+        self.labels[label] = Some(self.code.len());
+    }
+
+    fn constant(&mut self, value: Cell) -> usize {
+        self.constants.push(value);
+        return SCRATCH_SIZE + self.constants.len() - 1;
+    }
+
+    fn emit(&mut self, opcode: i64, operands: &[(i64, Cell)]) {
+        let mut instruction = opcode;
+        let mut factor = 100;
+        for (mode, _) in operands {
+            instruction += mode * factor;
+            factor *= 10;
+        }
+        self.code.push(Cell::Value(instruction));
+        for (_, cell) in operands {
+            self.code.push(*cell);
+        }
+    }
+
+    /// Reads a data cell in position or relative mode.
+    fn read_data<G: Gen>(&self, g: &mut G, index: usize) -> (i64, Cell) {
+        if bool::arbitrary(g) {
+            return (MODE_POSITION, Cell::Data(index));
+        }
+        return (MODE_RELATIVE, Cell::Relative(index, self.shift));
+    }
+
+    /// A value in any mode, read from a scratch cell unless immediate.
+    fn source<G: Gen>(&self, g: &mut G) -> (i64, Cell) {
+        if below(g, 3) == 0 {
+            return (MODE_IMMEDIATE, Cell::Value(small(g)));
+        }
+        let index = below(g, SCRATCH_SIZE);
+        return self.read_data(g, index);
+    }
+
+    /// A value known at generation time, in any mode.
+    fn known<G: Gen>(&mut self, g: &mut G, value: Cell) -> (i64, Cell) {
+        if below(g, 3) == 0 {
+            return (MODE_IMMEDIATE, value);
+        }
+        let index = self.constant(value);
+        return self.read_data(g, index);
+    }
+
+    fn destination<G: Gen>(&self, g: &mut G) -> (i64, Cell) {
+        let index = below(g, SCRATCH_SIZE);
+        return self.read_data(g, index);
+    }
+
+    /// One instruction that neither jumps nor moves the relative base.
+    /// Multiplication always has a factor between -2 and 2 so values grow at
+    /// most as fast as with additions.
+    fn simple<G: Gen>(&mut self, g: &mut G, repeat: usize) {
+        let opcodes = [
+            INSTRUCTION_ADD,
+            INSTRUCTION_MUL,
+            INSTRUCTION_READ,
+            INSTRUCTION_WRITE,
+            INSTRUCTION_LESS_THAN,
+            INSTRUCTION_EQUALS,
+        ];
+        let opcode = opcodes[below(g, opcodes.len())];
+        match opcode {
+            INSTRUCTION_READ => {
+                let d = self.destination(g);
+                self.emit(opcode, &[d]);
+                for _ in 0..repeat {
+                    self.inputs.push(small(g));
+                }
+            }
+            INSTRUCTION_WRITE => {
+                let a = self.source(g);
+                self.emit(opcode, &[a]);
+            }
+            INSTRUCTION_MUL => {
+                let a = self.source(g);
+                let factor = Cell::Value(below(g, 5) as i64 - 2);
+                let b = self.known(g, factor);
+                let d = self.destination(g);
+                self.emit(opcode, &[a, b, d]);
+            }
+            _ => {
+                let a = self.source(g);
+                let b = self.source(g);
+                let d = self.destination(g);
+                self.emit(opcode, &[a, b, d]);
+            }
+        }
+        self.steps += repeat;
+    }
+
+    /// Moves the relative base, runs a few instructions and moves it back.
+    fn shifted<G: Gen>(&mut self, g: &mut G) {
+        let shift = below(g, 4) as i64 + 1;
+        let a = self.known(g, Cell::Value(shift));
+        self.emit(INSTRUCTION_RELATIVE_ADD, &[a]);
+        self.shift += shift;
+        for _ in 0..below(g, 2) + 1 {
+            self.simple(g, 1);
+        }
+        let a = self.known(g, Cell::Value(-shift));
+        self.emit(INSTRUCTION_RELATIVE_ADD, &[a]);
+        self.shift -= shift;
+        self.steps += 2;
+    }
+
+    /// A conditional jump over the next few blocks.
+    fn forward_jump<G: Gen>(&mut self, g: &mut G) -> usize {
+        let opcode = if bool::arbitrary(g) {
+            INSTRUCTION_JUMP_IF_TRUE
+        } else {
+            INSTRUCTION_JUMP_IF_FALSE
+        };
+        let target = self.label();
+        let condition = self.source(g);
+        let target_cell = self.known(g, Cell::Label(target));
+        self.emit(opcode, &[condition, target_cell]);
+        self.steps += 1;
+        return target;
+    }
+
+    /// Runs a few instructions a fixed number of times, counting down a
+    /// dedicated cell.
+    fn counted_loop<G: Gen>(&mut self, g: &mut G) {
+        let iterations = below(g, MAX_LOOP_ITERATIONS) + 1;
+        let counter_cell = Cell::Counter(self.counters);
+        self.counters += 1;
+        self.emit(
+            INSTRUCTION_ADD,
+            &[
+                (MODE_IMMEDIATE, Cell::Value(iterations as i64)),
+                (MODE_IMMEDIATE, Cell::Value(0)),
+                (MODE_POSITION, counter_cell),
+            ],
+        );
+        let body = self.label();
+        self.place(body);
+        for _ in 0..below(g, 3) + 1 {
+            self.simple(g, iterations);
+        }
+        self.emit(
+            INSTRUCTION_ADD,
+            &[
+                (MODE_POSITION, counter_cell),
+                (MODE_IMMEDIATE, Cell::Value(-1)),
+                (MODE_POSITION, counter_cell),
+            ],
+        );
+        self.emit(
+            INSTRUCTION_JUMP_IF_TRUE,
+            &[
+                (MODE_POSITION, counter_cell),
+                (MODE_IMMEDIATE, Cell::Label(body)),
+            ],
+        );
+        self.steps += 1 + 2 * iterations;
+    }
+
+    fn build(self, data_start: usize) -> Vec<i64> {
+        let constants_start = data_start + SCRATCH_SIZE;
+        let counters_start = constants_start + self.constants.len();
+        let value = |cell: &Cell| match *cell {
+            Cell::Value(v) => v,
+            Cell::Label(label) => self.labels[label].unwrap() as i64,
+            Cell::Data(index) => (data_start + index) as i64,
+            Cell::Counter(index) => (counters_start + index) as i64,
+            Cell::Relative(index, shift) => index as i64 - shift,
+        };
+
+        let mut memory: Vec<i64> = self.code.iter().map(value).collect();
+        memory.push(INSTRUCTION_EXIT);
+        memory.extend(vec![0; SCRATCH_SIZE]);
+        memory.extend(self.constants.iter().map(value));
+        memory.extend(vec![0; self.counters]);
+        return memory;
+    }
+}
+
+impl Arbitrary for Program {
+    fn arbitrary<G: Gen>(g: &mut G) -> Program {
+        let mut builder = Builder {
+            code: vec![],
+            constants: vec![],
+            counters: 0,
+            labels: vec![],
+            inputs: vec![],
+            steps: 1,
+            shift: 0,
+        };
+        builder.emit(INSTRUCTION_RELATIVE_ADD, &[(MODE_IMMEDIATE, Cell::Data(0))]);
+
+        let blocks = below(g, g.size().max(1)) + 1;
+        let mut pending: Vec<(usize, usize)> = vec![];
+        for block in 0..blocks {
+            for (target, at) in &pending {
+                if *at == block {
+                    builder.place(*target);
+                }
+            }
+            if builder.steps >= MAX_STEPS {
+                continue;
+            }
+            match below(g, 6) {
+                0 => builder.shifted(g),
+                1 => {
+                    let target = builder.forward_jump(g);
+                    let at = block + 1 + below(g, 3);
+                    pending.push((target, at));
+                }
+                2 if builder.steps + 16 < MAX_STEPS => builder.counted_loop(g),
+                _ => builder.simple(g, 1),
+            }
+        }
+        // Jumps past the last block land on the halt.
+        for (target, _) in &pending {
+            if builder.labels[*target].is_none() {
+                builder.place(*target);
+            }
+        }
+
+        // Scratch cells start with small random values.
+        let data_start = builder.code.len() + 1;
+        let inputs = builder.inputs.clone();
+        let mut code = builder.build(data_start);
+        for cell in &mut code[data_start..data_start + SCRATCH_SIZE] {
+            *cell = small(g);
+        }
+
+        return Program {
+            code: code,
+            inputs: inputs,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer;
+    use crate::computer::{Computer, Level};
+    use crate::dialect::Dialect;
+    use crate::reader;
+    use crate::tracer::Tracer;
+    use quickcheck::StdThreadGen;
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    struct Coverage {
+        seen: Rc<RefCell<HashSet<i64>>>,
+    }
+
+    impl Tracer for Coverage {
+        fn trace(&mut self, pointer: usize, _: i64, memory: &[i64], _: &Dialect) {
+            self.seen.borrow_mut().insert(memory[pointer]);
+        }
+    }
+
+    #[test]
+    fn test_covers_opcodes_and_modes() {
+        let seen = Rc::new(RefCell::new(HashSet::new()));
+        let mut g = StdThreadGen::new(20);
+        for _ in 0..500 {
+            let program = Program::arbitrary(&mut g);
+            let mut c = computer::new(Level::Day9, program.code.len(), &program.code);
+            c.set_reader(Box::new(reader::sequence(&program.inputs)));
+            c.set_tracer(Box::new(Coverage { seen: seen.clone() }));
+            assert_eq!(0, c.execute());
+        }
+
+        let seen = seen.borrow();
+        let opcodes: HashSet<i64> = seen.iter().map(|i| i % 100).collect();
+        assert_eq!(opcodes.len(), 10);
+        for mode in &[MODE_POSITION, MODE_IMMEDIATE, MODE_RELATIVE] {
+            for digit in &[100, 1000] {
+                assert!(seen.iter().any(|i| i / digit % 10 == *mode));
+            }
+        }
+        assert!(seen.iter().any(|i| i / 10000 % 10 == MODE_RELATIVE));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::parse;
+use std::collections::HashMap;
+use std::ops::{Add, Index, IndexMut, Sub};
+
+/// A cell of a grid whose y axis points down, like the lines of a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+pub const ORIGIN: Point = Point { x: 0, y: 0 };
+
+pub fn point(x: i64, y: i64) -> Point {
+    return Point { x: x, y: y };
+}
+
+impl Point {
+    pub fn step(self, direction: Direction) -> Point {
+        return self + direction.offset();
+    }
+
+    /// The four cells sharing a side with this one, clockwise from above.
+    pub fn neighbours(self) -> [Point; 4] {
+        return [
+            self.step(Direction::Up),
+            self.step(Direction::Right),
+            self.step(Direction::Down),
+            self.step(Direction::Left),
+        ];
+    }
+
+    pub fn manhattan(self, other: Point) -> i64 {
+        return (self.x - other.x).abs() + (self.y - other.y).abs();
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    fn add(self, other: Point) -> Point {
+        return point(self.x + other.x, self.y + other.y);
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    fn sub(self, other: Point) -> Point {
+        return point(self.x - other.x, self.y - other.y);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+/// Every direction, clockwise from up.
+pub const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+impl Direction {
+    pub fn turn_left(self) -> Direction {
+        return match self {
+            Direction::Up => Direction::Left,
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+        };
+    }
+
+    pub fn turn_right(self) -> Direction {
+        return self.turn_left().turn_left().turn_left();
+    }
+
+    pub fn offset(self) -> Point {
+        return match self {
+            Direction::Up => point(0, -1),
+            Direction::Right => point(1, 0),
+            Direction::Down => point(0, 1),
+            Direction::Left => point(-1, 0),
+        };
+    }
+}
+
+/// A rectangle of cells from (0, 0), stored row after row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+/// A grid with every cell set to `fill`.
+pub fn grid<T: Clone>(width: usize, height: usize, fill: T) -> Grid<T> {
+    return from_cells(width, height, vec![fill; width * height]);
+}
+
+/// A grid of cells given row after row.
+pub fn from_cells<T>(width: usize, height: usize, cells: Vec<T>) -> Grid<T> {
+    assert_eq!(
+        cells.len(),
+        width * height,
+        "Not a {}x{} grid",
+        width,
+        height
+    );
+    return Grid {
+        width: width,
+        height: height,
+        cells: cells,
+    };
+}
+
+/// Reads a map of characters, each line a row, with `cell` giving the reason
+/// it rejects a character.
+pub fn parse<T>(text: &str, cell: impl FnMut(char) -> Result<T, String>) -> parse::Result<Grid<T>> {
+    let rows = parse::grid(text, cell)?;
+    let height = rows.len();
+    let width = rows.first().map_or(0, |row| row.len());
+    return Ok(from_cells(
+        width,
+        height,
+        rows.into_iter().flatten().collect(),
+    ));
+}
+
+impl<T> Grid<T> {
+    pub fn width(&self) -> usize {
+        return self.width;
+    }
+
+    pub fn height(&self) -> usize {
+        return self.height;
+    }
+
+    fn position(&self, point: Point) -> Option<usize> {
+        if !self.contains(point) {
+            return None;
+        }
+        return Some(point.y as usize * self.width + point.x as usize);
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        return point.x >= 0
+            && point.y >= 0
+            && (point.x as usize) < self.width
+            && (point.y as usize) < self.height;
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        return self.position(point).map(|i| &self.cells[i]);
+    }
+
+    pub fn get_mut(&mut self, point: Point) -> Option<&mut T> {
+        return self.position(point).map(move |i| &mut self.cells[i]);
+    }
+
+    /// Every point of the grid, row after row.
+    pub fn points(&self) -> impl Iterator<Item = Point> {
+        let width = self.width;
+        return (0..self.cells.len()).map(move |i| point((i % width) as i64, (i / width) as i64));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        return self.points().zip(self.cells.iter());
+    }
+
+    /// The grid as lines of text, each ending with a newline.
+    pub fn render(&self, glyph: impl Fn(&T) -> char) -> String {
+        let mut text = String::new();
+        for row in self.cells.chunks(self.width.max(1)) {
+            text.extend(row.iter().map(&glyph));
+            text.push('\n');
+        }
+        return text;
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, point: Point) -> &T {
+        match self.get(point) {
+            Some(cell) => return cell,
+            None => panic!("{:?} is outside of the grid", point),
+        }
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, point: Point) -> &mut T {
+        match self.get_mut(point) {
+            Some(cell) => return cell,
+            None => panic!("{:?} is outside of the grid", point),
+        }
+    }
+}
+
+/// Cells anywhere on an unbounded grid, for when few of them are known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sparse<T> {
+    pub cells: HashMap<Point, T>,
+}
+
+pub fn sparse<T>() -> Sparse<T> {
+    return Sparse {
+        cells: HashMap::new(),
+    };
+}
+
+impl<T> Sparse<T> {
+    pub fn insert(&mut self, point: Point, cell: T) -> Option<T> {
+        return self.cells.insert(point, cell);
+    }
+
+    pub fn get(&self, point: Point) -> Option<&T> {
+        return self.cells.get(&point);
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        return self.cells.contains_key(&point);
+    }
+
+    /// Corners of the smallest rectangle holding every cell, top left first.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let mut points = self.cells.keys();
+        let first = *points.next()?;
+        return Some(points.fold((first, first), |(low, high), p| {
+            (
+                point(low.x.min(p.x), low.y.min(p.y)),
+                point(high.x.max(p.x), high.y.max(p.y)),
+            )
+        }));
+    }
+
+    /// The rectangle of `bounds` as lines of text, each ending with a
+    /// newline, `glyph` getting `None` for the cells that are not there.
+    pub fn render(&self, glyph: impl Fn(Option<&T>) -> char) -> String {
+        let mut text = String::new();
+        if let Some((low, high)) = self.bounds() {
+            for y in low.y..=high.y {
+                text.extend((low.x..=high.x).map(|x| glyph(self.get(point(x, y)))));
+                text.push('\n');
+            }
+        }
+        return text;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_point() {
+        let p = point(2, -3);
+        assert_eq!(p.manhattan(ORIGIN), 5);
+        assert_eq!(p.step(Direction::Up), point(2, -4));
+        assert_eq!(p.neighbours()[1], point(3, -3));
+        assert_eq!(p - point(1, 1) + point(1, 1), p);
+        for direction in &DIRECTIONS {
+            assert_eq!(direction.turn_left().turn_right(), *direction);
+            assert_eq!(
+                p.step(*direction).step(direction.turn_left().turn_left()),
+                p
+            );
+        }
+    }
+
+    #[test]
+    fn test_grid() {
+        let cell = |c| match c {
+            '#' => Ok(true),
+            '.' => Ok(false),
+            _ => Err(String::from("expected '#' or '.'")),
+        };
+        let mut map = parse("#..\n.#.\n", cell).unwrap();
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert!(map[point(1, 1)]);
+        assert_eq!(map.get(point(3, 0)), None);
+        assert_eq!(map.get(point(-1, 0)), None);
+        map[point(2, 1)] = true;
+        assert_eq!(
+            map.iter()
+                .filter(|(_, c)| **c)
+                .map(|(p, _)| p)
+                .collect::<Vec<_>>(),
+            [point(0, 0), point(1, 1), point(2, 1)]
+        );
+        assert_eq!(map.render(|c| if *c { '#' } else { '.' }), "#..\n.##\n");
+        assert!(parse("#.\n#", cell).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the grid")]
+    fn test_outside() {
+        let mut cells = grid(2, 2, 0);
+        cells[point(0, 2)] = 1;
+    }
+
+    #[test]
+    fn test_sparse() {
+        let mut cells = sparse();
+        assert_eq!(cells.bounds(), None);
+        assert_eq!(cells.render(|_| '#'), "");
+        cells.insert(point(-1, 0), 'a');
+        cells.insert(point(1, 1), 'b');
+        assert!(cells.contains(point(1, 1)));
+        assert_eq!(cells.bounds(), Some((point(-1, 0), point(1, 1))));
+        assert_eq!(cells.render(|c| *c.unwrap_or(&'.')), "a..\n..b\n");
+    }
+}
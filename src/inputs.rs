@@ -0,0 +1,207 @@
+use crate::days;
+use crate::days::Puzzle;
+use std::fs;
+
+/// Where the inputs of the days are kept.
+pub const DIRECTORY: &str = "inputs";
+
+/// File of the input of a day, or of its example with `example`.
+pub fn path(directory: &str, day: u8, example: bool) -> String {
+    if example {
+        return format!("{}/day_{}_test.txt", directory, day);
+    }
+    return format!("{}/day_{}.txt", directory, day);
+}
+
+/// 64-bit FNV-1a hash, enough to tell two inputs apart.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    return hash;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Missing,
+    Empty,
+    /// The parser of the day rejects it, with the reason.
+    Invalid(String),
+    Valid,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub day: u8,
+    pub path: String,
+    pub example: bool,
+    pub size: u64,
+    pub checksum: Option<u64>,
+    pub status: Status,
+}
+
+/// Parses the input with the parser of the day, which also checks it has the
+/// shape the day needs. A parser that panics rejects the input.
+pub fn validate(puzzle: &dyn Puzzle, text: &str) -> Result<(), String> {
+    return days::catch(|| puzzle.check(text)).and_then(|checked| checked);
+}
+
+fn entry(puzzle: &dyn Puzzle, day: u8, path: String, example: bool) -> Entry {
+    let mut entry = Entry {
+        day: day,
+        path: path,
+        example: example,
+        size: 0,
+        checksum: None,
+        status: Status::Missing,
+    };
+    let bytes = match fs::read(&entry.path) {
+        Ok(bytes) => bytes,
+        Err(_) => return entry,
+    };
+    entry.size = bytes.len() as u64;
+    entry.checksum = Some(checksum(&bytes));
+    if bytes.is_empty() {
+        entry.status = Status::Empty;
+        return entry;
+    }
+    entry.status = match String::from_utf8(bytes) {
+        Ok(text) => match validate(puzzle, &text) {
+            Ok(()) => Status::Valid,
+            Err(e) => Status::Invalid(e),
+        },
+        Err(_) => Status::Invalid(String::from("Not UTF-8")),
+    };
+    return entry;
+}
+
+/// The input of every solved day, whether it exists or not, and the examples
+/// that exist.
+pub fn list(directory: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let examples = days::registry(true);
+    for (day, puzzle) in days::registry(false) {
+        entries.push(entry(&*puzzle, day, path(directory, day, false), false));
+        let example = entry(&*examples[&day], day, path(directory, day, true), true);
+        if example.status != Status::Missing {
+            entries.push(example);
+        }
+    }
+    return entries;
+}
+
+/// Validates an input and stores it as the input, or the example, of a day.
+/// A different input already there is only replaced with `force`. Returns
+/// where it was stored and its checksum.
+pub fn import(
+    directory: &str,
+    day: u8,
+    example: bool,
+    text: &str,
+    force: bool,
+) -> Result<(String, u64), String> {
+    let registry = days::registry(example);
+    let puzzle = match registry.get(&day) {
+        Some(puzzle) => puzzle,
+        None => return Err(format!("Day {} is not solved", day)),
+    };
+    if text.is_empty() {
+        return Err(String::from("The input is empty"));
+    }
+    validate(&**puzzle, text).map_err(|e| format!("Invalid input for day {}: {}", day, e))?;
+
+    let path = path(directory, day, example);
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if !existing.is_empty() && existing != text && !force {
+            return Err(format!("{} already holds another input", path));
+        }
+    }
+    fs::create_dir_all(directory).map_err(|e| format!("Cannot create {}: {}", directory, e))?;
+    fs::write(&path, text).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+    return Ok((path, checksum(text.as_bytes())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn directory(name: &str) -> String {
+        let directory = env::temp_dir().join(format!("inputs-{}-{}", name, process::id()));
+        return directory.to_str().unwrap().to_string();
+    }
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(b""), 0xcbf29ce484222325);
+        assert_eq!(checksum(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_import() {
+        let directory = directory("import");
+        assert_eq!(
+            import(&directory, 30, false, "1", false),
+            Err(String::from("Day 30 is not solved"))
+        );
+        assert!(import(&directory, 6, false, "COM)B\nB)YOU\nB)SAN\n", false).is_ok());
+        assert_eq!(
+            import(&directory, 6, false, "COM)YOU\nCOM)SAN\n", false),
+            Err(format!(
+                "{}/day_6.txt already holds another input",
+                directory
+            ))
+        );
+        let (path, sum) = import(&directory, 6, false, "COM)YOU\nCOM)SAN\n", true).unwrap();
+        assert_eq!(sum, checksum(b"COM)YOU\nCOM)SAN\n"));
+
+        fs::write(path.replace("day_6", "day_2"), "").unwrap();
+        let entries = list(&directory);
+        let status = |day| {
+            entries
+                .iter()
+                .find(|e| e.day == day)
+                .unwrap()
+                .status
+                .clone()
+        };
+        assert_eq!(status(6), Status::Valid);
+        assert_eq!(status(2), Status::Empty);
+        assert_eq!(status(1), Status::Missing);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    /// A day whose parser panics on every input.
+    struct Panicking {}
+
+    impl Puzzle for Panicking {
+        fn solve(&self, _: &str) -> Result<days::Solved, String> {
+            panic!("Cannot solve");
+        }
+
+        fn check(&self, _: &str) -> Result<(), String> {
+            panic!("Cannot parse");
+        }
+    }
+
+    #[test]
+    fn test_validate_panic() {
+        assert_eq!(
+            validate(&Panicking {}, "1"),
+            Err(String::from("Cannot parse"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_import() {
+        let directory = directory("invalid");
+        assert!(import(&directory, 6, false, "COM-B\n", false).is_err());
+        assert!(import(&directory, 6, false, "", false).is_err());
+        assert!(import(&directory, 6, false, "COM)B\nB)C\n", false).is_err());
+        assert!(import(&directory, 2, false, "1,2\n", false).is_err());
+        assert!(fs::read_dir(&directory).is_err());
+    }
+}
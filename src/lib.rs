@@ -0,0 +1,35 @@
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
+extern crate num;
+
+#[cfg(test)]
+extern crate quickcheck;
+#[cfg(test)]
+#[macro_use(quickcheck)]
+extern crate quickcheck_macros;
+
+pub mod allocator;
+pub mod analyzer;
+pub mod answers;
+pub mod asynchronous;
+pub mod computer;
+pub mod days;
+pub mod device;
+pub mod dialect;
+pub mod differential;
+pub mod disassembler;
+pub mod fuzz;
+#[cfg(test)]
+mod generator;
+pub mod grid;
+pub mod inputs;
+pub mod maze;
+pub mod optimizer;
+pub mod parse;
+pub mod reader;
+pub mod robot;
+pub mod scaffold;
+pub mod symbolic;
+pub mod tracer;
+pub mod writer;
+pub mod xorshift;
@@ -1,57 +1,591 @@
+#![allow(clippy::needless_return)]
+
+extern crate advent_of_code_2019;
 extern crate clap;
-extern crate num;
 
-#[cfg(test)]
-extern crate quickcheck;
-#[cfg(test)]
-#[macro_use(quickcheck)]
-extern crate quickcheck_macros;
+use advent_of_code_2019::allocator;
+use advent_of_code_2019::{
+    analyzer, answers, computer, days, disassembler, fuzz, inputs, optimizer, scaffold, xorshift,
+};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::ops::RangeInclusive;
+use std::panic;
+use std::process;
+use std::time::Duration;
+
+#[global_allocator]
+static ALLOCATOR: allocator::Counting = allocator::Counting;
+
+/// The crate this binary was built from, which holds the days, their inputs
+/// and their answers, wherever the binary runs.
+const ROOT: &str = env!("CARGO_MANIFEST_DIR");
 
-use clap::{App, Arg};
-mod computer;
-mod days;
-mod reader;
-mod writer;
+fn inputs_directory() -> String {
+    return format!("{}/{}", ROOT, inputs::DIRECTORY);
+}
 
 fn main() {
-    let (day, input) = get_args();
-    match day {
-        1 => days::day_1::run(input.as_ref()),
-        2 => days::day_2::run(input.as_ref()),
-        3 => days::day_3::run(input.as_ref()),
-        4 => days::day_4::run(),
-        5 => days::day_5::run(input.as_ref()),
-        6 => days::day_6::run(input.as_ref()),
-        7 => days::day_7::run(input.as_ref()),
-        8 => days::day_8::run(input.as_ref()),
-        9 => days::day_9::run(input.as_ref()),
-        10 => days::day_10::run(input.as_ref()),
-        _ => panic!("Cannot find wanted day."),
-    }
-}
-
-fn get_args() -> (u8, String) {
-    let matches = App::new("Advent of Code 2019")
+    let matches = get_args();
+    if let Some(matches) = matches.subcommand_matches("cfg") {
+        return run_cfg(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("optimize") {
+        return run_optimize(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("fuzz") {
+        return run_fuzz(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("inputs") {
+        return run_inputs(matches);
+    }
+    if let Some(matches) = matches.subcommand_matches("new-day") {
+        let day: u8 = matches.value_of("day").unwrap().parse().unwrap();
+        match scaffold::new_day(ROOT, day) {
+            Ok(written) => written.iter().for_each(|path| println!("{}", path)),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("disassemble") {
+        let program = computer::load_program(matches.value_of("input").unwrap());
+        print!("{}", disassembler::disassemble(&program));
+        return;
+    }
+
+    return run_days(&matches);
+}
+
+/// Parses a day, such as `3`, or an inclusive range of days, such as `3..7`,
+/// which must hold at least one solved day.
+fn parse_days(text: &str) -> Result<RangeInclusive<u8>, String> {
+    let parse = |day: &str| match day.parse::<u8>() {
+        Ok(day) if (1..=25).contains(&day) => Ok(day),
+        _ => Err(format!("{} is not a day between 1 and 25", day)),
+    };
+    let days = match text.find("..") {
+        Some(i) => parse(&text[..i])?..=parse(&text[i + 2..])?,
+        None => parse(text)?..=parse(text)?,
+    };
+    if days::registry(false).range(days.clone()).next().is_none() {
+        return Err(format!("No solved day in {}", text));
+    }
+    return Ok(days);
+}
+
+/// Validator of the arguments naming a single day.
+fn is_day(day: String) -> Result<(), String> {
+    match day.parse::<u8>() {
+        Ok(number) if (1..=25).contains(&number) => return Ok(()),
+        _ => return Err(format!("{} is not a day between 1 and 25", day)),
+    }
+}
+
+/// Reads an input file, or standard input for `-`. Errors name the path.
+fn read_input(input: &str) -> Result<String, String> {
+    if input == "-" {
+        let mut text = String::new();
+        return match io::stdin().read_to_string(&mut text) {
+            Ok(_) => Ok(text),
+            Err(e) => Err(format!("Cannot read standard input: {}", e)),
+        };
+    }
+    return fs::read_to_string(input).map_err(|e| format!("Cannot read {}: {}", input, e));
+}
+
+/// Where `--verify` looks up the answers for the default inputs, in `ROOT`.
+const ANSWERS: &str = "answers.toml";
+
+/// Solves the days and reports the answers and how long each part took, as
+/// a table or, with `--format json`, one JSON record per line. With
+/// `--verify`, answers are checked against the known ones. Exits with 1 if
+/// any day failed or any answer is wrong.
+fn run_days(matches: &ArgMatches) {
+    let registry = days::registry(matches.is_present("example"));
+    let days = match matches.value_of("day") {
+        Some(days) => parse_days(days).unwrap(),
+        None => 1..=25,
+    };
+    if days.start() != days.end() && matches.is_present("input") {
+        eprintln!("--input needs a single day");
+        process::exit(2);
+    }
+
+    let expected = if matches.is_present("verify") {
+        match answers::load(&format!("{}/{}", ROOT, ANSWERS)) {
+            Ok(answers) => Some(answers),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    } else {
+        None
+    };
+
+    let json = matches.value_of("format") == Some("json");
+    let repeat: usize = matches.value_of("repeat").unwrap().parse().unwrap();
+    if !json {
+        let mut header = format!("{:>3} {:>5} {:>10}", "Day", "Part", "Time");
+        if repeat > 1 {
+            header += &format!(" {:>10} {:>10}", "Min", "Max");
+        }
+        println!("{} {:>10}  Answer", header, "Peak");
+    }
+    let mut failed = false;
+    for (day, puzzle) in registry.range(days) {
+        let input = match matches.value_of("input") {
+            Some(input) => String::from(input),
+            None => inputs::path(&inputs_directory(), *day, matches.is_present("example")),
+        };
+        // A day that panics is reported and the others still run.
+        let runs = read_input(&input).and_then(|text| {
+            days::catch(|| (0..repeat).map(|_| puzzle.solve(&text)).collect()).and_then(|runs| runs)
+        });
+        let runs: Vec<days::Solved> = match runs {
+            Ok(runs) => runs,
+            Err(e) => {
+                failed = true;
+                if json {
+                    println!(
+                        "{{\"day\": {}, \"input_path\": {}, \"error\": {}}}",
+                        day,
+                        json_string(&input),
+                        json_string(&e)
+                    );
+                } else {
+                    eprintln!("{}", e);
+                    println!("{:>3} {:>5} {:>10}  failed", day, "-", "-");
+                }
+                continue;
+            }
+        };
+
+        let parse = summarize(runs.iter().map(|r| r.parse).collect());
+        if !json {
+            print_row(*day, "parse", &parse, repeat, "", None);
+        }
+        for (i, answer) in runs[0].answers.iter().enumerate() {
+            let part = i as u8 + 1;
+            let cost = summarize(runs.iter().map(|r| r.answers[i].cost).collect());
+            let known = expected.as_ref().and_then(|e| e.get(&(*day, part)));
+            if known.is_some() && known != Some(&answer.answer) {
+                failed = true;
+            }
+            let expected = expected.as_ref().map(|_| known);
+            if json {
+                print_record(
+                    *day,
+                    part,
+                    &answer.answer,
+                    &cost,
+                    &parse,
+                    repeat,
+                    &input,
+                    expected,
+                );
+            } else {
+                let label = part.to_string();
+                print_row(*day, &label, &cost, repeat, &answer.answer, expected);
+            }
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+/// Costs of a step over repeated runs.
+struct Summary {
+    min: Duration,
+    median: Duration,
+    max: Duration,
+    peak_bytes: usize,
+}
+
+fn summarize(costs: Vec<days::Cost>) -> Summary {
+    let mut times: Vec<Duration> = costs.iter().map(|c| c.elapsed).collect();
+    times.sort();
+    return Summary {
+        min: times[0],
+        median: times[times.len() / 2],
+        max: times[times.len() - 1],
+        peak_bytes: costs.iter().map(|c| c.peak_bytes).max().unwrap(),
+    };
+}
+
+fn milliseconds(duration: Duration) -> String {
+    return format!("{:.1}ms", duration.as_secs_f64() * 1000.0);
+}
+
+fn bytes(bytes: usize) -> String {
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    if bytes < 1024 * 1024 {
+        return format!("{:.1}KiB", bytes as f64 / 1024.0);
+    }
+    return format!("{:.1}MiB", bytes as f64 / (1024.0 * 1024.0));
+}
+
+/// Prints a row of the table, followed by the known answer if it differs.
+/// `expected` is only given when verifying.
+fn print_row(
+    day: u8,
+    part: &str,
+    summary: &Summary,
+    repeat: usize,
+    answer: &str,
+    expected: Option<Option<&String>>,
+) {
+    let mut prefix = format!(
+        "{:>3} {:>5} {:>10}",
+        day,
+        part,
+        milliseconds(summary.median)
+    );
+    if repeat > 1 {
+        prefix += &format!(
+            " {:>10} {:>10}",
+            milliseconds(summary.min),
+            milliseconds(summary.max)
+        );
+    }
+    prefix += &format!(" {:>10}  ", bytes(summary.peak_bytes));
+    let indent = " ".repeat(prefix.len());
+
+    // Answers such as images continue on the next lines.
+    let mut lines = answer.trim_end().lines();
+    let first = prefix + lines.next().unwrap_or("");
+    println!("{}", first.trim_end());
+    for line in lines {
+        println!("{}{}", indent, line);
+    }
+    match expected {
+        Some(Some(known)) if known != answer => {
+            println!("{}Wrong, expected:", indent);
+            for line in known.trim_end().lines() {
+                println!("{}{}", indent, line);
+            }
+        }
+        Some(None) => println!("{}No known answer", indent),
+        _ => {}
+    }
+}
+
+/// Prints a part as a JSON record, with the median time and, for repeated
+/// runs, the fastest and slowest. When verifying, `correct` says whether the
+/// answer matches the known one, or is null if none is known.
+#[allow(clippy::too_many_arguments)]
+fn print_record(
+    day: u8,
+    part: u8,
+    answer: &str,
+    summary: &Summary,
+    parse: &Summary,
+    repeat: usize,
+    input: &str,
+    expected: Option<Option<&String>>,
+) {
+    let mut extra = String::new();
+    if repeat > 1 {
+        extra += &format!(
+            ", \"runs\": {}, \"min_ms\": {:.3}, \"max_ms\": {:.3}",
+            repeat,
+            summary.min.as_secs_f64() * 1000.0,
+            summary.max.as_secs_f64() * 1000.0
+        );
+    }
+    match expected {
+        Some(Some(known)) => extra += &format!(", \"correct\": {}", known == answer),
+        Some(None) => extra += ", \"correct\": null",
+        None => {}
+    };
+    println!(
+        "{{\"day\": {}, \"part\": {}, \"answer\": {}, \"elapsed_ms\": {:.3}, \"peak_bytes\": {}, \
+         \"parse_ms\": {:.3}, \"input_path\": {}{}}}",
+        day,
+        part,
+        json_string(answer),
+        summary.median.as_secs_f64() * 1000.0,
+        summary.peak_bytes,
+        parse.median.as_secs_f64() * 1000.0,
+        json_string(input),
+        extra
+    );
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json += "\\\"",
+            '\\' => json += "\\\\",
+            '\n' => json += "\\n",
+            '\r' => json += "\\r",
+            '\t' => json += "\\t",
+            c if (c as u32) < 0x20 => json += &format!("\\u{:04x}", c as u32),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    return json;
+}
+
+fn run_inputs(matches: &ArgMatches) {
+    if let Some(matches) = matches.subcommand_matches("import") {
+        let day: u8 = matches.value_of("day").unwrap().parse().unwrap();
+        let source = matches.value_of("FILE").unwrap();
+        let imported = read_input(source).and_then(|text| {
+            inputs::import(
+                &inputs_directory(),
+                day,
+                matches.is_present("example"),
+                &text,
+                matches.is_present("force"),
+            )
+        });
+        match imported {
+            Ok((path, checksum)) => println!("{} {:016x}", path, checksum),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut problems = false;
+    println!(
+        "{:>3}  {:<24} {:>8}  {:<16}  Status",
+        "Day", "Input", "Size", "Checksum"
+    );
+    for entry in inputs::list(&inputs_directory()) {
+        let status = match &entry.status {
+            inputs::Status::Missing => String::from("missing"),
+            inputs::Status::Empty => String::from("empty"),
+            inputs::Status::Invalid(e) => format!("invalid: {}", e.lines().next().unwrap_or("")),
+            inputs::Status::Valid => String::from("ok"),
+        };
+        if entry.status != inputs::Status::Valid {
+            problems = true;
+        }
+        let checksum = entry
+            .checksum
+            .map_or(String::new(), |c| format!("{:016x}", c));
+        // Paths are listed relative to the crate.
+        let path = entry.path[ROOT.len()..].trim_start_matches('/');
+        println!(
+            "{:>3}  {:<24} {:>8}  {:<16}  {}",
+            entry.day, path, entry.size, checksum, status
+        );
+    }
+    if problems {
+        process::exit(1);
+    }
+}
+
+fn run_cfg(matches: &ArgMatches) {
+    let program = computer::load_program(matches.value_of("input").unwrap());
+    let cfg = analyzer::build(&program);
+    for address in &cfg.indirect_jumps {
+        eprintln!("Unresolved indirect jump at {}", address);
+    }
+    for (start, end) in &cfg.unreachable {
+        eprintln!("Unreachable from entry: {}..{}", start, end);
+    }
+    for modification in &cfg.self_modifications {
+        eprintln!(
+            "Self-modifying write at {} into code at {}",
+            modification.address, modification.target
+        );
+    }
+    print!("{}", cfg.to_dot());
+}
+
+fn run_optimize(matches: &ArgMatches) {
+    let program = computer::load_program(matches.value_of("input").unwrap());
+    let optimization = optimizer::optimize(&program);
+    eprintln!(
+        "Folded {} constants, canonicalized {} copies, removed {} jumps, {} -> {} cells",
+        optimization.folded,
+        optimization.copies,
+        optimization.removed_jumps,
+        program.len(),
+        optimization.program.len()
+    );
+    let cells: Vec<String> = optimization.program.iter().map(|v| v.to_string()).collect();
+    println!("{}", cells.join(","));
+}
+
+fn run_fuzz(matches: &ArgMatches) {
+    let seed: u64 = matches.value_of("seed").unwrap().parse().unwrap();
+    let runs: u64 = matches.value_of("runs").unwrap().parse().unwrap();
+    let mut random = xorshift::random(seed);
+    let (mut halted, mut faults, mut exhausted) = (0, 0, 0);
+    for _ in 0..runs {
+        let length = random.below(64) as usize + 1;
+        let program = fuzz::random_program(&mut random, length);
+        let inputs = fuzz::random_program(&mut random, 4);
+        match panic::catch_unwind(|| fuzz::run(&program, &inputs)) {
+            Ok(computer::Outcome::Halted) => halted += 1,
+            Ok(computer::Outcome::Fault(_)) => faults += 1,
+            Ok(computer::Outcome::LimitExceeded(_)) => exhausted += 1,
+            Ok(computer::Outcome::Paused) => unreachable!("Fuzzed runs never pause"),
+            Err(_) => {
+                eprintln!("Program: {:?}", program);
+                eprintln!("Inputs: {:?}", inputs);
+                process::exit(1);
+            }
+        }
+    }
+    println!(
+        "{} runs: {} halted, {} faulted, {} exhausted the budget",
+        runs, halted, faults, exhausted
+    );
+}
+
+fn program_subcommand(name: &'static str, about: &'static str) -> App<'static, 'static> {
+    return SubCommand::with_name(name).about(about).arg(
+        Arg::with_name("input")
+            .short("i")
+            .long("input")
+            .value_name("FILE")
+            .help("Intcode program")
+            .required(true),
+    );
+}
+
+fn get_args() -> ArgMatches<'static> {
+    return App::new("Advent of Code 2019")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("day")
                 .short("d")
                 .long("day")
-                .value_name("NUMBER")
-                .help("Day to execute, 1-25")
-                .required(true)
-                .possible_values(&["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"]),
+                .value_name("DAYS")
+                .help("Day to execute, such as 3, or range of days, such as 3..7")
+                .required_unless("all")
+                .validator(|days| parse_days(&days).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("all")
+                .long("all")
+                .help("Executes every day")
+                .conflicts_with_all(&["day", "input"]),
+        )
+        .arg(
+            Arg::with_name("verify")
+                .long("verify")
+                .help("Checks the answers against answers.toml")
+                .conflicts_with_all(&["input", "example"]),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format, json prints one record per part and line")
+                .possible_values(&["table", "json"])
+                .default_value("table"),
+        )
+        .arg(
+            Arg::with_name("repeat")
+                .long("repeat")
+                .value_name("N")
+                .help("Solves every day N times and reports the median, min and max times")
+                .default_value("1")
+                .validator(|n| match n.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("expected a positive number")),
+                }),
+        )
+        .arg(
+            Arg::with_name("example")
+                .long("example")
+                .help("Uses the example inputs, inputs/day_N_test.txt")
+                .conflicts_with("input"),
         )
         .arg(
             Arg::with_name("input")
                 .short("i")
                 .long("input")
                 .value_name("FILE")
-                .help("Input file")
+                .help("Input file, - for standard input, inputs/day_N.txt by default")
                 .required(false),
         )
+        .subcommand(program_subcommand(
+            "cfg",
+            "Prints the control-flow graph of an Intcode program in DOT format",
+        ))
+        .subcommand(program_subcommand(
+            "optimize",
+            "Prints a peephole-optimized version of an Intcode program",
+        ))
+        .subcommand(
+            SubCommand::with_name("fuzz")
+                .about("Runs random programs and stops at the first interpreter panic")
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .value_name("NUMBER")
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::with_name("runs")
+                        .long("runs")
+                        .value_name("NUMBER")
+                        .default_value("10000"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("inputs")
+                .about("Lists the inputs of every day and checks them with the day's parser")
+                .subcommand(
+                    SubCommand::with_name("import")
+                        .about("Checks an input and stores it as the input of a day")
+                        .arg(
+                            Arg::with_name("day")
+                                .short("d")
+                                .long("day")
+                                .value_name("NUMBER")
+                                .required(true)
+                                .validator(is_day),
+                        )
+                        .arg(
+                            Arg::with_name("example")
+                                .long("example")
+                                .help("Stores it as the example input"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .help("Replaces a different input"),
+                        )
+                        .arg(
+                            Arg::with_name("FILE")
+                                .help("Input to import, - for standard input")
+                                .default_value("-"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("new-day")
+                .about("Creates and registers the module and input files of a new day")
+                .arg(
+                    Arg::with_name("day")
+                        .value_name("NUMBER")
+                        .required(true)
+                        .validator(is_day),
+                ),
+        )
+        .subcommand(program_subcommand(
+            "disassemble",
+            "Prints an Intcode program as a listing of instructions",
+        ))
         .get_matches();
-
-    let day: u8 = matches.value_of("day").unwrap().parse().unwrap();
-    let input: String = String::from(matches.value_of("input").unwrap_or(""));
-    return (day, input);
 }
@@ -0,0 +1,174 @@
+use crate::computer::{Computer, IntComputer, Outcome};
+use crate::grid::{Point, Sparse, DIRECTIONS, ORIGIN};
+use crate::robot;
+use crate::robot::STATUS_WALL;
+use std::collections::{HashMap, VecDeque};
+
+/// Status of the cell a droid starts on, which it never reports itself.
+pub const STATUS_OPEN: i64 = 1;
+
+/// Every cell a droid can reach, with the status it reported on entering it,
+/// and the walls around them. Positions are relative to the start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Maze {
+    pub tiles: Sparse<i64>,
+}
+
+/// Maps the whole maze of a droid program waiting for its first move. Each
+/// reached cell keeps a snapshot of the machine, from which every unknown
+/// neighbour is tried with a single move, so the droid never walks back.
+/// A droid that halts or faults instead of asking for its next move is an
+/// error. The machine is left in the state of the last move.
+pub fn explore(computer: &mut IntComputer) -> Result<Maze, Outcome> {
+    let mut robot = robot::robot();
+    robot.tiles.insert(ORIGIN, STATUS_OPEN);
+    let mut queue = VecDeque::new();
+    queue.push_back((ORIGIN, computer.snapshot()));
+
+    while let Some((position, snapshot)) = queue.pop_front() {
+        for heading in &DIRECTIONS {
+            let target = position.step(*heading);
+            if robot.get_tile(target).is_some() {
+                continue;
+            }
+            computer.restore(&snapshot);
+            robot.position = position;
+            robot.pending.clear();
+            let mut next = Some(*heading);
+            let mut rules = robot::explorer(Box::new(move |_: &robot::Robot| next.take()));
+            match robot::run(computer, &mut robot, &mut rules) {
+                Outcome::Paused => {}
+                outcome => return Err(outcome),
+            }
+            if robot.position == target {
+                queue.push_back((target, computer.snapshot()));
+            }
+        }
+    }
+    return Ok(Maze { tiles: robot.tiles });
+}
+
+impl Maze {
+    pub fn is_open(&self, position: Point) -> bool {
+        return self
+            .tiles
+            .get(position)
+            .is_some_and(|status| *status != STATUS_WALL);
+    }
+
+    /// Some cell with the given status, such as the oxygen system.
+    pub fn find(&self, status: i64) -> Option<Point> {
+        return self
+            .tiles
+            .cells
+            .iter()
+            .find(|(_, s)| **s == status)
+            .map(|(position, _)| *position);
+    }
+
+    /// Number of moves from the cell to every open cell reachable from it.
+    pub fn distances(&self, from: Point) -> HashMap<Point, usize> {
+        let mut distances = HashMap::new();
+        if !self.is_open(from) {
+            return distances;
+        }
+        distances.insert(from, 0);
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        while let Some(position) = queue.pop_front() {
+            let distance = distances[&position];
+            for next in position.neighbours().iter().cloned() {
+                if self.is_open(next) && !distances.contains_key(&next) {
+                    distances.insert(next, distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        return distances;
+    }
+
+    /// Fewest moves between two cells, if one can be reached from the other.
+    pub fn shortest_path(&self, from: Point, to: Point) -> Option<usize> {
+        return self.distances(from).get(&to).cloned();
+    }
+
+    /// Steps it takes something spreading one cell per step from the cell to
+    /// fill everything reachable.
+    pub fn fill_time(&self, from: Point) -> usize {
+        return self.distances(from).values().cloned().max().unwrap_or(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer;
+    use crate::computer::Level;
+    use crate::disassembler::assemble;
+    use crate::grid::point;
+
+    /// A droid in the maze of the example, walls as '#' or ' ', oxygen as
+    /// 'O', starting at 'D'. The relative base always points at the droid's
+    /// cell of the map, which is stored after the code.
+    fn droid(map: &str) -> IntComputer {
+        let width = map.lines().next().unwrap().len();
+        let mut cells = vec![];
+        let mut start = 0;
+        for c in map.lines().flat_map(|l| l.chars()) {
+            if c == 'D' {
+                start = cells.len();
+            }
+            cells.push(match c {
+                '.' | 'D' => 1,
+                'O' => 2,
+                _ => 0,
+            });
+        }
+        let mut offsets = String::new();
+        for (i, offset) in [-(width as i64), width as i64, -1, 1].iter().enumerate() {
+            let block = 4 + 14 * i;
+            offsets += &format!(
+                "eq [82], {}, [83]\njz [83], {}\nadd 0, {}, [84]\njnz 1, 61\n",
+                i + 1,
+                block + 14,
+                offset
+            );
+        }
+        let source = format!(
+            "arb {}\nin [82]\n{}hlt\narb [84]\njz [rb+0], 71\nout [rb+0]\njnz 1, 2\n\
+             out 0\nmul [84], -1, [84]\narb [84]\njnz 1, 2\ndata 0\ndata 0\ndata 0\n",
+            85 + start,
+            offsets
+        );
+        let mut program = assemble(&source).unwrap();
+        assert_eq!(program.len(), 85);
+        program.extend(cells);
+        return computer::new(Level::Day9, program.len(), &program);
+    }
+
+    #[test]
+    fn test_explore() {
+        let mut computer = droid(" ##   \n#D.## \n#.#..#\n#.O.# \n ###  ");
+        let maze = explore(&mut computer).unwrap();
+
+        let oxygen = maze.find(2).unwrap();
+        assert_eq!(oxygen, point(1, 2));
+        assert_eq!(
+            maze.tiles
+                .cells
+                .values()
+                .filter(|s| **s != STATUS_WALL)
+                .count(),
+            8
+        );
+        assert_eq!(maze.shortest_path(ORIGIN, oxygen), Some(3));
+        assert_eq!(maze.shortest_path(ORIGIN, point(5, 5)), None);
+        assert_eq!(maze.fill_time(oxygen), 4);
+    }
+
+    #[test]
+    fn test_halting_droid() {
+        let mut computer = computer::new(Level::Day9, 3, &[3, 0, 99]);
+        assert_eq!(explore(&mut computer), Err(Outcome::Halted));
+    }
+}
@@ -0,0 +1,357 @@
+use crate::analyzer;
+use crate::analyzer::{Cfg, Terminator};
+use crate::computer::{
+    INSTRUCTION_ADD, INSTRUCTION_EQUALS, INSTRUCTION_JUMP_IF_FALSE, INSTRUCTION_JUMP_IF_TRUE,
+    INSTRUCTION_LESS_THAN, INSTRUCTION_MUL,
+};
+use crate::disassembler::{decode, Instruction, MODE_IMMEDIATE, MODE_POSITION, MODE_RELATIVE};
+use std::collections::{BTreeMap, HashSet};
+
+pub struct Optimization {
+    pub program: Vec<i64>,
+    /// Arithmetic and comparisons on immediates replaced by a constant copy.
+    pub folded: usize,
+    /// Copies written as `mul x, 1` or `add 0, x` rewritten to `add x, 0`.
+    pub copies: usize,
+    /// Jumps that never fire or land on the next instruction.
+    pub removed_jumps: usize,
+}
+
+/// Rewrites a program into one with the same observable I/O.
+///
+/// An instruction is only touched when every address the program can read or
+/// write is known statically and none of them points into the instruction.
+/// Programs with relative-mode operands, indirect jumps or invalid reachable
+/// instructions are returned unchanged. Jumps are only removed from programs
+/// that never write into their code, as moving the code would leave the
+/// addresses such writes store pointing at the old cells.
+pub fn optimize(program: &[i64]) -> Optimization {
+    let mut optimization = Optimization {
+        program: program.to_vec(),
+        folded: 0,
+        copies: 0,
+        removed_jumps: 0,
+    };
+
+    let cfg = analyzer::build(program);
+    let instructions = match reachable_instructions(program, &cfg) {
+        Some(instructions) => instructions,
+        None => return optimization,
+    };
+    let relocatable = cfg.self_modifications.is_empty();
+
+    let mut accessed = HashSet::new();
+    for instruction in instructions.values() {
+        for (i, parameter) in instruction.parameters.iter().enumerate() {
+            if instruction.modes[i] == MODE_POSITION {
+                accessed.insert(*parameter);
+            }
+        }
+    }
+
+    let mut removed = HashSet::new();
+    for instruction in instructions.values() {
+        let untouched = (instruction.address..instruction.next())
+            .all(|cell| !accessed.contains(&(cell as i64)));
+        if !untouched {
+            continue;
+        }
+
+        if relocatable && is_removable_jump(instruction) {
+            removed.extend(instruction.address..instruction.next());
+            optimization.removed_jumps += 1;
+        } else if let Some((rewritten, folded)) = rewrite(instruction) {
+            if folded {
+                optimization.folded += 1;
+            } else {
+                optimization.copies += 1;
+            }
+            let address = instruction.address;
+            optimization.program[address..address + 4].copy_from_slice(&rewritten);
+        }
+    }
+
+    if !removed.is_empty() {
+        optimization.program = compact(&optimization.program, &instructions, &removed);
+    }
+    return optimization;
+}
+
+/// Decoded instructions reachable from the entry point, or `None` when the
+/// addresses the program touches cannot be bounded statically.
+fn reachable_instructions(program: &[i64], cfg: &Cfg) -> Option<BTreeMap<usize, Instruction>> {
+    if !cfg.indirect_jumps.is_empty() {
+        return None;
+    }
+
+    let mut instructions = BTreeMap::new();
+    let mut owner = vec![None; program.len()];
+    for block in cfg.blocks.values() {
+        if let Terminator::Invalid(_) = block.terminator {
+            return None;
+        }
+        for instruction in &block.instructions {
+            if instruction.modes.contains(&MODE_RELATIVE) {
+                return None;
+            }
+            // Overlapping instructions would need two encodings of one cell.
+            for cell in &mut owner[instruction.address..instruction.next()] {
+                if cell.is_some() && *cell != Some(instruction.address) {
+                    return None;
+                }
+                *cell = Some(instruction.address);
+            }
+            instructions.insert(instruction.address, instruction.clone());
+        }
+    }
+    return Some(instructions);
+}
+
+fn is_removable_jump(instruction: &Instruction) -> bool {
+    let jump_if_true = match instruction.opcode {
+        INSTRUCTION_JUMP_IF_TRUE => true,
+        INSTRUCTION_JUMP_IF_FALSE => false,
+        _ => return false,
+    };
+    let (condition, target) = (instruction.parameters[0], instruction.parameters[1]);
+    if instruction.modes[1] == MODE_IMMEDIATE && target == instruction.next() as i64 {
+        return true;
+    }
+    return instruction.modes[0] == MODE_IMMEDIATE && (condition != 0) != jump_if_true;
+}
+
+/// Canonical form of an arithmetic instruction and whether it was folded to a
+/// constant, or `None` when it is already canonical or cannot be simplified.
+fn rewrite(instruction: &Instruction) -> Option<([i64; 4], bool)> {
+    let opcode = instruction.opcode;
+    if ![
+        INSTRUCTION_ADD,
+        INSTRUCTION_MUL,
+        INSTRUCTION_LESS_THAN,
+        INSTRUCTION_EQUALS,
+    ]
+    .contains(&opcode)
+    {
+        return None;
+    }
+
+    let modes = instruction.modes;
+    let p = &instruction.parameters;
+    let immediate = |i: usize| {
+        if modes[i] == MODE_IMMEDIATE {
+            Some(p[i])
+        } else {
+            None
+        }
+    };
+    let copy = |mode: i64, value: i64| {
+        [
+            INSTRUCTION_ADD + 100 * mode + 1000 * MODE_IMMEDIATE + 10000 * modes[2],
+            value,
+            0,
+            p[2],
+        ]
+    };
+
+    if let (Some(a), Some(b)) = (immediate(0), immediate(1)) {
+        let value = match opcode {
+            INSTRUCTION_ADD => a.checked_add(b)?,
+            INSTRUCTION_MUL => a.checked_mul(b)?,
+            INSTRUCTION_LESS_THAN => (a < b) as i64,
+            _ => (a == b) as i64,
+        };
+        if opcode == INSTRUCTION_ADD && b == 0 {
+            return None;
+        }
+        return Some((copy(MODE_IMMEDIATE, value), true));
+    }
+
+    return match (opcode, immediate(0), immediate(1)) {
+        (INSTRUCTION_MUL, Some(0), _) | (INSTRUCTION_MUL, _, Some(0)) => {
+            Some((copy(MODE_IMMEDIATE, 0), true))
+        }
+        (INSTRUCTION_MUL, _, Some(1)) => Some((copy(modes[0], p[0]), false)),
+        (INSTRUCTION_MUL, Some(1), _) | (INSTRUCTION_ADD, Some(0), _) => {
+            Some((copy(modes[1], p[1]), false))
+        }
+        _ => None,
+    };
+}
+
+/// Drops the `removed` cells and shifts every static address past them.
+fn compact(
+    program: &[i64],
+    instructions: &BTreeMap<usize, Instruction>,
+    removed: &HashSet<usize>,
+) -> Vec<i64> {
+    let mut sorted: Vec<usize> = removed.iter().cloned().collect();
+    sorted.sort();
+    let relocate = |address: i64| {
+        if address < 0 {
+            return address;
+        }
+        let shift = sorted.iter().take_while(|r| (**r as i64) < address).count();
+        return address - shift as i64;
+    };
+
+    let mut relocated = program.to_vec();
+    for address in instructions.keys() {
+        if removed.contains(address) {
+            continue;
+        }
+        let instruction = decode(program, *address).unwrap();
+        let is_jump = instruction.opcode == INSTRUCTION_JUMP_IF_TRUE
+            || instruction.opcode == INSTRUCTION_JUMP_IF_FALSE;
+        for (i, parameter) in instruction.parameters.iter().enumerate() {
+            let is_target = is_jump && i == 1;
+            if instruction.modes[i] == MODE_POSITION || is_target {
+                relocated[address + 1 + i] = relocate(*parameter);
+            }
+        }
+    }
+
+    return relocated
+        .into_iter()
+        .enumerate()
+        .filter(|(address, _)| !removed.contains(address))
+        .map(|(_, value)| value)
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer;
+    use crate::computer::{Computer, Level};
+    use crate::reader;
+    use crate::writer;
+    use std::sync::mpsc::channel;
+
+    fn run(program: &[i64], inputs: &[i64]) -> Vec<i64> {
+        let (tx_in, rx_in) = channel();
+        let (tx_out, rx_out) = channel();
+        for input in inputs {
+            tx_in.send(*input).unwrap();
+        }
+        let mut c = computer::new(Level::Day9, program.len() + 100, program);
+        c.set_reader(Box::new(reader::channel(rx_in)));
+        c.register_writer(Box::new(writer::channel(tx_out)));
+        assert_eq!(0, c.execute());
+        return rx_out.try_iter().collect();
+    }
+
+    fn assert_same_io(program: &[i64], inputs: &[i64]) -> Optimization {
+        let optimization = optimize(program);
+        assert_eq!(run(program, inputs), run(&optimization.program, inputs));
+        return optimization;
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        // add 2, 3, [9]; mul 4, 5, [10]; out [9]; out [10]; hlt
+        let program = vec![1101, 2, 3, 13, 1102, 4, 5, 14, 4, 13, 4, 14, 99, 0, 0];
+        let optimization = assert_same_io(&program, &[]);
+        assert_eq!(optimization.folded, 2);
+        assert_eq!(
+            &optimization.program[..8],
+            &[1101, 5, 0, 13, 1101, 20, 0, 14]
+        );
+    }
+
+    #[test]
+    fn test_copy_idioms() {
+        // in [13]; mul [13], 1, [14]; add 0, [14], [15]; out [15]; hlt
+        let program = vec![3, 13, 1002, 13, 1, 14, 101, 0, 14, 15, 4, 15, 99, 0, 0, 0];
+        let optimization = assert_same_io(&program, &[42]);
+        assert_eq!(optimization.copies, 2);
+        assert_eq!(
+            &optimization.program[2..10],
+            &[1001, 13, 0, 14, 1001, 14, 0, 15]
+        );
+    }
+
+    #[test]
+    fn test_remove_jumps() {
+        // 0: jnz [12], 3 (jump to next)
+        // 3: jz 1, 0 (never fires)
+        // 6: out [12]
+        // 8: jnz 1, 11
+        // 11: hlt
+        let program = vec![1005, 12, 3, 1106, 1, 0, 4, 12, 1105, 1, 11, 99, 7];
+        let optimization = assert_same_io(&program, &[]);
+        assert_eq!(optimization.removed_jumps, 3);
+        assert_eq!(optimization.program, vec![4, 3, 99, 7]);
+    }
+
+    #[test]
+    fn test_relocate_targets() {
+        // 0: in [14]
+        // 2: jz 0, 5 (jump to next)
+        // 5: jz [14], 11
+        // 8: out [14]
+        // 10: hlt
+        // 11: out 1
+        // 13: hlt
+        let program = vec![3, 14, 1106, 0, 5, 1006, 14, 11, 4, 14, 99, 104, 1, 99, 0];
+        for input in &[0, 5] {
+            let optimization = assert_same_io(&program, &[*input]);
+            assert_eq!(optimization.removed_jumps, 1);
+        }
+    }
+
+    #[test]
+    fn test_self_modifying_untouched() {
+        // add [0], [0], [3] reads its own opcode, as day 2 programs do.
+        let program = vec![1, 0, 0, 3, 99];
+        let optimization = assert_same_io(&program, &[]);
+        assert_eq!(optimization.program, program);
+
+        // add 2, 3, [5] patches the first operand of the multiplication.
+        let program = vec![1101, 2, 3, 5, 1102, 4, 5, 11, 4, 11, 99, 0];
+        let optimization = assert_same_io(&program, &[]);
+        assert_eq!(&optimization.program[4..8], &program[4..8]);
+    }
+
+    #[test]
+    fn test_written_operand_kept() {
+        // 0: jnz 0, 3 (never fires)
+        // 3: add 12, 0, [8] (points the output at 12)
+        // 7: out [10]
+        // 9: hlt
+        let program = vec![1105, 0, 3, 1101, 12, 0, 8, 4, 10, 99, 0, 0, 42];
+        assert_eq!(run(&program, &[]), [42]);
+        let optimization = assert_same_io(&program, &[]);
+        assert_eq!(optimization.removed_jumps, 0);
+    }
+
+    #[test]
+    fn test_relative_mode_untouched() {
+        let program = vec![109, 1, 1101, 2, 3, 0, 204, -1, 99];
+        let optimization = optimize(&program);
+        assert_eq!(optimization.program, program);
+    }
+
+    #[test]
+    fn test_diagnostic_program() {
+        let program = computer::load_program("inputs/day_5.txt");
+        assert_same_io(&program, &[1]);
+        assert_same_io(&program, &[5]);
+    }
+
+    #[test]
+    fn test_comparison_examples() {
+        let programs = vec![
+            vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8],
+            vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8],
+            vec![3, 3, 1108, -1, 8, 3, 4, 3, 99],
+            vec![3, 3, 1107, -1, 8, 3, 4, 3, 99],
+            vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9],
+            vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1],
+        ];
+        for program in &programs {
+            for input in &[-3, 0, 7, 8, 9] {
+                assert_same_io(program, &[*input]);
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Where and why an input was rejected. Lines and columns count from 1 and
+/// are relative to the text given to the parser that failed, until the
+/// combinators around it move them to where that text was in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub fn error(line: usize, column: usize, message: &str) -> Error {
+    return Error {
+        line: line,
+        column: column,
+        message: String::from(message),
+    };
+}
+
+impl Error {
+    /// The error of a text that starts after `lines` lines and, on its first
+    /// line, after `columns` columns.
+    fn shift(mut self, lines: usize, columns: usize) -> Error {
+        if self.line == 1 {
+            self.column += columns;
+        }
+        self.line += lines;
+        return self;
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(
+            f,
+            "Line {}, column {}: {}",
+            self.line, self.column, self.message
+        );
+    }
+}
+
+impl From<Error> for String {
+    fn from(error: Error) -> String {
+        return error.to_string();
+    }
+}
+
+/// Parses every line with `item`. Trailing blank lines are ignored, any
+/// other blank line is given to `item`.
+pub fn lines<'a, T>(text: &'a str, mut item: impl FnMut(&'a str) -> Result<T>) -> Result<Vec<T>> {
+    return text
+        .trim_end()
+        .lines()
+        .enumerate()
+        .map(|(n, line)| item(line).map_err(|e| e.shift(n, 0)))
+        .collect();
+}
+
+/// Parses a text of a single line, ignoring the newline at its end.
+pub fn line<'a, T>(text: &'a str, item: impl FnOnce(&'a str) -> Result<T>) -> Result<T> {
+    let text = text.trim_end();
+    if text.contains('\n') {
+        return Err(error(2, 1, "expected a single line"));
+    }
+    return item(text);
+}
+
+/// Parses every field of a line between separators with `item`.
+pub fn separated<'a, T>(
+    text: &'a str,
+    separator: char,
+    mut item: impl FnMut(&'a str) -> Result<T>,
+) -> Result<Vec<T>> {
+    let mut column = 0;
+    let mut items = Vec::new();
+    for field in text.split(separator) {
+        items.push(item(field).map_err(|e| e.shift(0, column))?);
+        column += field.chars().count() + 1;
+    }
+    return Ok(items);
+}
+
+/// Parses both sides of the first separator of a line.
+pub fn pair<'a, A, B>(
+    text: &'a str,
+    separator: &str,
+    left: impl FnOnce(&'a str) -> Result<A>,
+    right: impl FnOnce(&'a str) -> Result<B>,
+) -> Result<(A, B)> {
+    let end = match text.find(separator) {
+        Some(end) => end,
+        None => {
+            let message = format!("expected '{}'", separator);
+            return Err(error(1, text.chars().count() + 1, &message));
+        }
+    };
+    let rest = &text[end + separator.len()..];
+    let columns = text[..end + separator.len()].chars().count();
+    return Ok((
+        left(&text[..end])?,
+        right(rest).map_err(|e| e.shift(0, columns))?,
+    ));
+}
+
+/// Splits the first character of a field from what follows it, which is
+/// parsed with `item`.
+pub fn prefixed<'a, T>(
+    text: &'a str,
+    item: impl FnOnce(&'a str) -> Result<T>,
+) -> Result<(char, T)> {
+    let first = match text.chars().next() {
+        Some(first) => first,
+        None => return Err(error(1, 1, "expected a prefix")),
+    };
+    let rest = &text[first.len_utf8()..];
+    return Ok((first, item(rest).map_err(|e| e.shift(0, 1))?));
+}
+
+pub fn number<T: FromStr>(text: &str) -> Result<T> {
+    return text
+        .parse()
+        .map_err(|_| error(1, 1, &format!("expected a number: '{}'", text)));
+}
+
+/// A non-empty run of letters and digits.
+pub fn name(text: &str) -> Result<&str> {
+    if text.is_empty() {
+        return Err(error(1, 1, "expected a name"));
+    }
+    match text.chars().position(|c| !c.is_alphanumeric()) {
+        Some(column) => return Err(error(1, column + 1, "expected a letter or digit")),
+        None => return Ok(text),
+    }
+}
+
+/// A line of decimal digits.
+pub fn digits(text: &str) -> Result<Vec<u32>> {
+    return text
+        .chars()
+        .enumerate()
+        .map(|(column, c)| {
+            c.to_digit(10)
+                .ok_or_else(|| error(1, column + 1, &format!("expected a digit: '{}'", c)))
+        })
+        .collect();
+}
+
+/// Parses every character of a rectangle of lines with `cell`, which gives
+/// the reason it rejects a character.
+pub fn grid<T>(
+    text: &str,
+    mut cell: impl FnMut(char) -> std::result::Result<T, String>,
+) -> Result<Vec<Vec<T>>> {
+    let mut width = None;
+    return lines(text, |line| {
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(column, c)| cell(c).map_err(|message| error(1, column + 1, &message)))
+            .collect::<Result<Vec<T>>>()?;
+        match width {
+            Some(width) if width != row.len() => {
+                let message = format!("expected {} columns", width);
+                return Err(error(1, row.len().min(width) + 1, &message));
+            }
+            _ => width = Some(row.len()),
+        }
+        return Ok(row);
+    });
+}
+
+/// An Intcode program: a single line of comma-separated integers.
+pub fn program(text: &str) -> Result<Vec<i64>> {
+    return line(text, |line| separated(line, ',', number));
+}
+
+/// An Intcode program of at least `length` integers, for days that set
+/// cells of it before running it.
+pub fn program_of(text: &str, length: usize) -> Result<Vec<i64>> {
+    let program = program(text)?;
+    if program.len() < length {
+        let message = format!("expected at least {} integers", length);
+        return Err(error(1, text.trim_end().chars().count() + 1, &message));
+    }
+    return Ok(program);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program() {
+        assert_eq!(program("1,-2,3\n"), Ok(vec![1, -2, 3]));
+        assert_eq!(
+            program("1,2,x3\n"),
+            Err(error(1, 5, "expected a number: 'x3'"))
+        );
+        assert_eq!(
+            program("1,2\n3"),
+            Err(error(2, 1, "expected a single line"))
+        );
+        assert_eq!(
+            program("1,2,").unwrap_err().to_string(),
+            "Line 1, column 5: expected a number: ''"
+        );
+        assert_eq!(program_of("1,2,3", 3), Ok(vec![1, 2, 3]));
+        assert_eq!(
+            program_of("1,2\n", 3),
+            Err(error(1, 4, "expected at least 3 integers"))
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        assert_eq!(lines("1.5\n2\n\n", number::<f64>), Ok(vec![1.5, 2.0]));
+        assert_eq!(
+            lines("1\n\n2", number::<f64>),
+            Err(error(2, 1, "expected a number: ''"))
+        );
+        assert_eq!(
+            lines("A)B\nB-C", |l| pair(l, ")", name, name)),
+            Err(error(2, 4, "expected ')'"))
+        );
+        assert_eq!(
+            lines("A)B\nB)C!", |l| pair(l, ")", name, name)),
+            Err(error(2, 4, "expected a letter or digit"))
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let path = |l| separated(l, ',', |f| prefixed(f, number::<u32>));
+        assert_eq!(path("R8,U5"), Ok(vec![('R', 8), ('U', 5)]));
+        assert_eq!(path("R8,U"), Err(error(1, 5, "expected a number: ''")));
+        assert_eq!(path("R8,"), Err(error(1, 4, "expected a prefix")));
+        assert_eq!(digits("1203"), Ok(vec![1, 2, 0, 3]));
+        assert_eq!(digits("12a"), Err(error(1, 3, "expected a digit: 'a'")));
+    }
+
+    #[test]
+    fn test_grid() {
+        let cell = |c| match c {
+            '#' => Ok(true),
+            '.' => Ok(false),
+            _ => Err(format!("unknown cell: '{}'", c)),
+        };
+        assert_eq!(
+            grid(".#\n#.\n", cell),
+            Ok(vec![vec![false, true], vec![true, false]])
+        );
+        assert_eq!(
+            grid(".#\n#?\n", cell),
+            Err(error(2, 2, "unknown cell: '?'"))
+        );
+        assert_eq!(grid(".#\n#", cell), Err(error(2, 2, "expected 2 columns")));
+    }
+}
@@ -1,3 +1,6 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
 use std::sync::mpsc::Receiver;
 
 pub trait Reader {
@@ -62,3 +65,34 @@ impl Reader for Channel {
 pub fn channel(receiver: Receiver<i64>) -> Channel {
     return Channel { receiver: receiver };
 }
+
+pub struct Sequence {
+    values: VecDeque<i64>,
+}
+
+impl Reader for Sequence {
+    fn read(&mut self) -> i64 {
+        return self.values.pop_front().expect("No more values to read");
+    }
+}
+
+pub fn sequence(values: &[i64]) -> Sequence {
+    return Sequence {
+        values: values.iter().cloned().collect(),
+    };
+}
+
+/// Reads the value the host put in the slot before the machine asked for it.
+pub struct Slot {
+    value: Rc<Cell<Option<i64>>>,
+}
+
+impl Reader for Slot {
+    fn read(&mut self) -> i64 {
+        return self.value.take().expect("No value in the slot");
+    }
+}
+
+pub fn slot(value: Rc<Cell<Option<i64>>>) -> Slot {
+    return Slot { value: value };
+}
@@ -0,0 +1,311 @@
+use crate::computer::{Computer, IntComputer, Outcome, INSTRUCTION_READ};
+use crate::grid;
+use crate::grid::{Direction, Point, Sparse, ORIGIN};
+use crate::reader;
+use crate::writer;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Status a droid reports when the cell it tried to enter is a wall.
+pub const STATUS_WALL: i64 = 0;
+
+/// Movement command of the droid puzzles: north 1, south 2, west 3 and east
+/// 4, north being up.
+pub fn command(direction: Direction) -> i64 {
+    return match direction {
+        Direction::Up => 1,
+        Direction::Down => 2,
+        Direction::Left => 3,
+        Direction::Right => 4,
+    };
+}
+
+fn arrow(direction: Direction) -> char {
+    return match direction {
+        Direction::Up => '^',
+        Direction::Right => '>',
+        Direction::Down => 'v',
+        Direction::Left => '<',
+    };
+}
+
+/// Position and heading of a robot and what it learned about the tiles.
+#[derive(Debug, Clone)]
+pub struct Robot {
+    pub position: Point,
+    pub heading: Direction,
+    /// Colours for a painter, droid statuses for an explorer.
+    pub tiles: Sparse<i64>,
+    pub visited: HashSet<Point>,
+    /// Outputs of the machine that do not make a whole action yet, kept for
+    /// when a paused run resumes.
+    pub pending: Vec<i64>,
+}
+
+/// A robot at the origin, facing up.
+pub fn robot() -> Robot {
+    let mut visited = HashSet::new();
+    visited.insert(ORIGIN);
+    return Robot {
+        position: ORIGIN,
+        heading: Direction::Up,
+        tiles: grid::sparse(),
+        visited: visited,
+        pending: Vec::new(),
+    };
+}
+
+impl Robot {
+    pub fn get_tile(&self, position: Point) -> Option<i64> {
+        return self.tiles.get(position).cloned();
+    }
+
+    pub fn move_to(&mut self, position: Point) {
+        self.position = position;
+        self.visited.insert(position);
+    }
+
+    /// Known tiles and the robot, drawn as an arrow, in the smallest
+    /// rectangle holding both. Unknown cells are blank.
+    pub fn render(&self, glyph: &dyn Fn(i64) -> char) -> String {
+        let mut cells = grid::sparse();
+        for position in &self.visited {
+            cells.insert(*position, ' ');
+        }
+        for (position, tile) in &self.tiles.cells {
+            cells.insert(*position, glyph(*tile));
+        }
+        cells.insert(self.position, arrow(self.heading));
+        return cells.render(|cell| *cell.unwrap_or(&' '));
+    }
+}
+
+/// What the inputs and outputs of the machine mean for the robot.
+pub trait Rules {
+    /// Next input for the machine, or `None` to pause the run.
+    fn input(&mut self, robot: &Robot) -> Option<i64>;
+    /// Number of outputs that make up one action.
+    fn arity(&self) -> usize;
+    fn output(&mut self, robot: &mut Robot, values: &[i64]);
+}
+
+/// Reads the colour under the robot, which is 0 unless painted. Outputs a
+/// colour to paint and a turn, 0 for left and 1 for right, after which the
+/// robot moves one tile forward.
+pub struct Painter {}
+
+impl Rules for Painter {
+    fn input(&mut self, robot: &Robot) -> Option<i64> {
+        return Some(robot.get_tile(robot.position).unwrap_or(0));
+    }
+
+    fn arity(&self) -> usize {
+        return 2;
+    }
+
+    fn output(&mut self, robot: &mut Robot, values: &[i64]) {
+        robot.tiles.insert(robot.position, values[0]);
+        robot.heading = match values[1] {
+            0 => robot.heading.turn_left(),
+            1 => robot.heading.turn_right(),
+            turn => panic!("Unknown turn {}", turn),
+        };
+        let next = robot.position.step(robot.heading);
+        robot.move_to(next);
+    }
+}
+
+/// Picks the next move of a droid, or `None` to stop exploring.
+pub type Controller = Box<dyn FnMut(&Robot) -> Option<Direction>>;
+
+/// Sends the moves a controller picks and records the status the droid
+/// replies with as the tile it tried to enter. Any status but
+/// `STATUS_WALL` means the droid moved.
+pub struct Explorer {
+    controller: Controller,
+    pending: Option<Direction>,
+}
+
+pub fn explorer(controller: Controller) -> Explorer {
+    return Explorer {
+        controller: controller,
+        pending: None,
+    };
+}
+
+impl Rules for Explorer {
+    fn input(&mut self, robot: &Robot) -> Option<i64> {
+        self.pending = (self.controller)(robot);
+        return self.pending.map(command);
+    }
+
+    fn arity(&self) -> usize {
+        return 1;
+    }
+
+    fn output(&mut self, robot: &mut Robot, values: &[i64]) {
+        let heading = self.pending.take().expect("Status without a move");
+        let target = robot.position.step(heading);
+        robot.heading = heading;
+        robot.tiles.insert(target, values[0]);
+        if values[0] != STATUS_WALL {
+            robot.move_to(target);
+        }
+    }
+}
+
+/// Runs the machine with the robot until it halts, faults or the rules pause
+/// it, which is reported as `Outcome::Paused`. A paused machine resumes with
+/// the next call, outputs of an unfinished action staying with the robot.
+/// Replaces the reader and the writers of the machine.
+pub fn run(computer: &mut IntComputer, robot: &mut Robot, rules: &mut dyn Rules) -> Outcome {
+    let slot = Rc::new(Cell::new(None));
+    let outputs = Rc::new(RefCell::new(Vec::new()));
+    computer.set_reader(Box::new(reader::slot(slot.clone())));
+    computer.clear_writers();
+    computer.register_writer(Box::new(writer::buffer(outputs.clone())));
+
+    loop {
+        if computer.next_opcode() == Some(INSTRUCTION_READ) {
+            match rules.input(robot) {
+                Some(value) => slot.set(Some(value)),
+                None => return Outcome::Paused,
+            }
+        }
+        let halted = computer.step();
+        robot.pending.extend(outputs.borrow_mut().drain(..));
+        while robot.pending.len() >= rules.arity() {
+            let action: Vec<i64> = robot.pending.drain(..rules.arity()).collect();
+            rules.output(robot, &action);
+        }
+        match halted {
+            Ok(false) => {}
+            Ok(true) => return Outcome::Halted,
+            Err(fault) => return Outcome::Fault(fault),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer;
+    use crate::computer::Level;
+    use crate::disassembler::assemble;
+    use crate::grid::point;
+
+    #[test]
+    fn test_painter() {
+        // The example of the painting robot: read the colour, then paint and
+        // turn, seven times.
+        let mut program = vec![];
+        for (colour, turn) in &[(1, 0), (0, 0), (1, 0), (1, 0), (0, 1), (1, 0), (1, 0)] {
+            program.extend(&[3, 100, 104, *colour, 104, *turn]);
+        }
+        program.push(99);
+        let mut c = computer::new(Level::Day9, 101, &program);
+        let mut robot = robot();
+        assert_eq!(run(&mut c, &mut robot, &mut Painter {}), Outcome::Halted);
+
+        assert_eq!(robot.tiles.cells.len(), 6);
+        assert_eq!(robot.position, point(0, -1));
+        assert_eq!(robot.heading, Direction::Left);
+        let glyph = |colour| if colour == 1 { '#' } else { '.' };
+        assert_eq!(robot.render(&glyph), " <#\n..#\n## \n");
+    }
+
+    /// Pauses at the first read and records the actions.
+    struct Pausing {
+        paused: bool,
+        actions: Vec<Vec<i64>>,
+    }
+
+    impl Rules for Pausing {
+        fn input(&mut self, _: &Robot) -> Option<i64> {
+            if !self.paused {
+                self.paused = true;
+                return None;
+            }
+            return Some(0);
+        }
+
+        fn arity(&self) -> usize {
+            return 2;
+        }
+
+        fn output(&mut self, _: &mut Robot, values: &[i64]) {
+            self.actions.push(values.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_pause_within_action() {
+        // out 1; in [100]; out 0; hlt
+        let program = [104, 1, 3, 100, 104, 0, 99];
+        let mut c = computer::new(Level::Day9, 101, &program);
+        let mut robot = robot();
+        let mut rules = Pausing {
+            paused: false,
+            actions: vec![],
+        };
+        let outcome = run(&mut c, &mut robot, &mut rules);
+        assert_eq!(outcome, Outcome::Paused);
+        assert_eq!(robot.pending, [1]);
+        assert_eq!(run(&mut c, &mut robot, &mut rules), Outcome::Halted);
+        assert_eq!(rules.actions, [[1, 0]]);
+        assert!(robot.pending.is_empty());
+    }
+
+    #[test]
+    fn test_explorer() {
+        // A corridor droid: east moves until x = 3, which holds the oxygen
+        // system, and everything else is a wall.
+        let program = assemble(
+            "
+            0: in [100]
+            2: eq [100], 4, [101]
+            6: jz [101], 33
+            9: lt [102], 3, [101]
+            13: jz [101], 33
+            16: add [102], 1, [102]
+            20: eq [102], 3, [101]
+            24: add [101], 1, [101]
+            28: out [101]
+            30: jnz 1, 0
+            33: out 0
+            35: jnz 1, 0
+            ",
+        )
+        .unwrap();
+        let mut c = computer::new(Level::Day9, 103, &program);
+        let mut robot = robot();
+        let mut rules = explorer(Box::new(|robot: &Robot| {
+            let ahead = robot.position.step(Direction::Right);
+            match robot.get_tile(ahead) {
+                None => Some(Direction::Right),
+                Some(_) => None,
+            }
+        }));
+        let outcome = run(&mut c, &mut robot, &mut rules);
+
+        assert_eq!(outcome, Outcome::Paused);
+        assert_eq!(robot.position, point(3, 0));
+        assert_eq!(robot.get_tile(point(3, 0)), Some(2));
+        assert_eq!(robot.get_tile(point(4, 0)), Some(STATUS_WALL));
+        assert_eq!(robot.visited.len(), 4);
+
+        // The paused droid resumes where it stopped.
+        let mut tried = false;
+        let mut rules = explorer(Box::new(move |_: &Robot| {
+            if tried {
+                return None;
+            }
+            tried = true;
+            return Some(Direction::Left);
+        }));
+        run(&mut c, &mut robot, &mut rules);
+        assert_eq!(robot.get_tile(point(2, 0)), Some(STATUS_WALL));
+        assert_eq!(robot.position, point(3, 0));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::inputs;
+use std::fs;
+use std::path::Path;
+
+/// Starting point of a new day, which reads lines and solves nothing yet.
+const TEMPLATE: &str = "use crate::days::Solution;
+use crate::parse;
+
+pub struct Day {}
+
+impl Solution for Day {
+    type Input = Vec<String>;
+    type Answer1 = String;
+    type Answer2 = String;
+
+    fn parse(&self, text: &str) -> parse::Result<Vec<String>> {
+        return parse::lines(text, |line| Ok(String::from(line)));
+    }
+
+    fn part_1(&self, _input: &Vec<String>) -> String {
+        return String::from(\"Not solved yet\");
+    }
+
+    fn part_2(&self, _input: &Vec<String>) -> String {
+        return String::from(\"Not solved yet\");
+    }
+}
+";
+
+/// Inserts a line among those starting with `prefix`, before the first one
+/// that `key` orders after it, or after the last one.
+fn insert_sorted<K: Ord>(
+    lines: &mut Vec<String>,
+    prefix: &str,
+    line: String,
+    key: impl Fn(&str) -> K,
+) -> Result<(), String> {
+    let matching: Vec<usize> = (0..lines.len())
+        .filter(|i| lines[*i].starts_with(prefix))
+        .collect();
+    let last = match matching.last() {
+        Some(last) => *last,
+        None => return Err(String::from("Cannot find where days are registered")),
+    };
+    let position = matching
+        .into_iter()
+        .find(|i| key(&lines[*i]) > key(&line))
+        .unwrap_or(last + 1);
+    lines.insert(position, line);
+    return Ok(());
+}
+
+/// Adds the module of a day and its registry entry to `days/mod.rs`. Modules
+/// stay in the order rustfmt keeps them in, entries in the order of days.
+pub fn register(module: &str, day: u8) -> Result<String, String> {
+    let declaration = format!("pub mod day_{};", day);
+    let entry = format!("    days.insert({}, Box::new(day_{}::Day {{}}));", day, day);
+    let mut lines: Vec<String> = module.lines().map(String::from).collect();
+    if lines.contains(&declaration) {
+        return Err(format!("Day {} is already registered", day));
+    }
+    insert_sorted(&mut lines, "pub mod day_", declaration, |line| {
+        String::from(line.trim_end_matches(';'))
+    })?;
+    insert_sorted(&mut lines, "    days.insert(", entry, |line| {
+        line["    days.insert(".len()..]
+            .split(',')
+            .next()
+            .and_then(|day| day.parse::<u8>().ok())
+    })?;
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    return Ok(text);
+}
+
+/// Creates the module of a new day from the template and registers it, and
+/// creates its input and example files if they do not exist, all under
+/// `root`, the root of the crate. Returns the files it wrote.
+pub fn new_day(root: &str, day: u8) -> Result<Vec<String>, String> {
+    let root = Path::new(root);
+    let source = root.join(format!("src/days/day_{}.rs", day));
+    if source.exists() {
+        return Err(format!("{} already exists", source.display()));
+    }
+    let module = root.join("src/days/mod.rs");
+    let text = fs::read_to_string(&module)
+        .map_err(|e| format!("Cannot read {}: {}", module.display(), e))?;
+    let text = register(&text, day)?;
+
+    let write = |path: &Path, text: &str| {
+        fs::write(path, text).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    };
+    write(&source, TEMPLATE)?;
+    write(&module, &text)?;
+    let mut written = vec![source.display().to_string(), module.display().to_string()];
+    let directory = root.join(inputs::DIRECTORY);
+    fs::create_dir_all(&directory)
+        .map_err(|e| format!("Cannot create {}: {}", directory.display(), e))?;
+    for example in &[false, true] {
+        let input = Path::new(&inputs::path(directory.to_str().unwrap(), day, *example)).to_owned();
+        if !input.exists() {
+            write(&input, "")?;
+            written.push(input.display().to_string());
+        }
+    }
+    return Ok(written);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_register_in_order() {
+        let module = "pub mod day_1;\npub mod day_25;\n\n    days.insert(1, Box::new(day_1::Day {}));\n    days.insert(25, Box::new(day_25::Day {}));\n";
+        assert_eq!(
+            register(module, 12).unwrap(),
+            "pub mod day_1;\npub mod day_12;\npub mod day_25;\n\n    days.insert(1, Box::new(day_1::Day {}));\n    days.insert(12, Box::new(day_12::Day {}));\n    days.insert(25, Box::new(day_25::Day {}));\n"
+        );
+        let registered = register(include_str!("days/mod.rs"), 11).unwrap();
+        assert!(registered.contains("pub mod day_10;\npub mod day_11;\npub mod day_2;\n"));
+        assert!(registered.contains("(10, Box::new(day_10::Day {}));\n    days.insert(11,"));
+    }
+
+    #[test]
+    fn test_register() {
+        let module = "pub mod day_1;\n\nfn registry() {\n    days.insert(1, Box::new(day_1::Day {}));\n    return days;\n}\n";
+        let registered = register(module, 2).unwrap();
+        assert_eq!(
+            registered,
+            "pub mod day_1;\npub mod day_2;\n\nfn registry() {\n    days.insert(1, Box::new(day_1::Day {}));\n    days.insert(2, Box::new(day_2::Day {}));\n    return days;\n}\n"
+        );
+        assert_eq!(
+            register(&registered, 2),
+            Err(String::from("Day 2 is already registered"))
+        );
+        assert!(register("", 2).is_err());
+    }
+
+    #[test]
+    fn test_new_day() {
+        let root = env::temp_dir().join(format!("scaffold-{}", process::id()));
+        fs::create_dir_all(root.join("src/days")).unwrap();
+        fs::write(root.join("src/days/mod.rs"), include_str!("days/mod.rs")).unwrap();
+        let root = root.to_str().unwrap();
+
+        let written = new_day(root, 25).unwrap();
+        assert_eq!(written.len(), 4);
+        let module = fs::read_to_string(format!("{}/src/days/mod.rs", root)).unwrap();
+        assert!(module.contains("pub mod day_25;\n"));
+        assert!(module.contains("days.insert(25, Box::new(day_25::Day {}));"));
+        assert_eq!(
+            fs::read_to_string(format!("{}/inputs/day_25_test.txt", root)).unwrap(),
+            ""
+        );
+        assert!(new_day(root, 25).is_err());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
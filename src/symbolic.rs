@@ -0,0 +1,501 @@
+use crate::computer::{
+    parse_instruction, INSTRUCTION_ADD, INSTRUCTION_EQUALS, INSTRUCTION_EXIT,
+    INSTRUCTION_JUMP_IF_FALSE, INSTRUCTION_JUMP_IF_TRUE, INSTRUCTION_LESS_THAN, INSTRUCTION_MUL,
+    INSTRUCTION_READ, INSTRUCTION_RELATIVE_ADD, INSTRUCTION_WRITE,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+
+const MAX_STEPS: usize = 1_000_000;
+
+/// `constant + sum(coefficient * variable)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Linear {
+    pub constant: i64,
+    pub coefficients: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Linear(Linear),
+    /// A value that cannot be expressed linearly in the variables, such as the
+    /// product of two variables or a load through a symbolic address.
+    Opaque(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// A conditional jump whose condition depends on a variable.
+    SymbolicBranch(usize),
+    /// An instruction fetch, write address or jump target that depends on a
+    /// variable.
+    SymbolicAddress(usize),
+    InvalidAddress(usize),
+    InvalidInstruction(usize),
+    /// An address or the relative base overflowing an `i64`.
+    Overflow(usize),
+    StepLimit,
+}
+
+pub struct Machine {
+    memory: Vec<Expr>,
+    pointer: usize,
+    relative_base: i64,
+    inputs: usize,
+}
+
+pub fn new(program: &[i64]) -> Machine {
+    return Machine {
+        memory: program.iter().map(|v| Expr::constant(*v)).collect(),
+        pointer: 0,
+        relative_base: 0,
+        inputs: 0,
+    };
+}
+
+impl Linear {
+    /// The sum, unless a coefficient or the constant overflows.
+    fn add(&self, other: &Linear) -> Option<Linear> {
+        let mut coefficients = self.coefficients.clone();
+        for (name, c) in &other.coefficients {
+            let sum = coefficients.entry(name.clone()).or_insert(0);
+            *sum = sum.checked_add(*c)?;
+        }
+        coefficients.retain(|_, c| *c != 0);
+        return Some(Linear {
+            constant: self.constant.checked_add(other.constant)?,
+            coefficients: coefficients,
+        });
+    }
+
+    /// The product, unless a coefficient or the constant overflows.
+    fn scale(&self, factor: i64) -> Option<Linear> {
+        let mut coefficients = BTreeMap::new();
+        if factor != 0 {
+            for (name, c) in &self.coefficients {
+                coefficients.insert(name.clone(), c.checked_mul(factor)?);
+            }
+        }
+        return Some(Linear {
+            constant: self.constant.checked_mul(factor)?,
+            coefficients: coefficients,
+        });
+    }
+
+    pub fn as_constant(&self) -> Option<i64> {
+        if self.coefficients.is_empty() {
+            Some(self.constant)
+        } else {
+            None
+        }
+    }
+
+    /// Finds values for the variables, in the order given by `bounds`, such
+    /// that the expression equals `target`. Every variable but the last is
+    /// enumerated within its inclusive bounds, the last one is solved for.
+    pub fn solve(&self, target: i64, bounds: &[(&str, i64, i64)]) -> Option<Vec<i64>> {
+        for name in self.coefficients.keys() {
+            if !bounds.iter().any(|(n, _, _)| n == name) {
+                return None;
+            }
+        }
+        let remaining = target.checked_sub(self.constant)?;
+        return self.solve_from(remaining, bounds, &mut Vec::new());
+    }
+
+    fn solve_from(
+        &self,
+        remaining: i64,
+        bounds: &[(&str, i64, i64)],
+        values: &mut Vec<i64>,
+    ) -> Option<Vec<i64>> {
+        let (name, low, high) = bounds[values.len()];
+        let coefficient = *self.coefficients.get(name).unwrap_or(&0);
+
+        if values.len() + 1 == bounds.len() {
+            let value = if coefficient == 0 {
+                if remaining != 0 {
+                    return None;
+                }
+                low
+            } else {
+                if remaining.checked_rem(coefficient)? != 0 {
+                    return None;
+                }
+                remaining.checked_div(coefficient)?
+            };
+            if value < low || high < value {
+                return None;
+            }
+            let mut solution = values.clone();
+            solution.push(value);
+            return Some(solution);
+        }
+
+        for value in low..=high {
+            // Values whose term overflows cannot reach an `i64` target.
+            let rest = match coefficient
+                .checked_mul(value)
+                .and_then(|term| remaining.checked_sub(term))
+            {
+                Some(rest) => rest,
+                None => continue,
+            };
+            values.push(value);
+            let solution = self.solve_from(rest, bounds, values);
+            values.pop();
+            if solution.is_some() {
+                return solution;
+            }
+        }
+        return None;
+    }
+}
+
+impl Expr {
+    pub fn constant(value: i64) -> Expr {
+        return Expr::Linear(Linear {
+            constant: value,
+            coefficients: BTreeMap::new(),
+        });
+    }
+
+    pub fn variable(name: &str) -> Expr {
+        let mut coefficients = BTreeMap::new();
+        coefficients.insert(String::from(name), 1);
+        return Expr::Linear(Linear {
+            constant: 0,
+            coefficients: coefficients,
+        });
+    }
+
+    pub fn as_constant(&self) -> Option<i64> {
+        match self {
+            Expr::Linear(linear) => linear.as_constant(),
+            Expr::Opaque(_) => None,
+        }
+    }
+
+    fn add(&self, other: &Expr) -> Expr {
+        match (self, other) {
+            (Expr::Linear(a), Expr::Linear(b)) => match a.add(b) {
+                Some(sum) => Expr::Linear(sum),
+                None => Expr::Opaque(format!("({} + {})", self, other)),
+            },
+            _ => Expr::Opaque(format!("({} + {})", self, other)),
+        }
+    }
+
+    fn mul(&self, other: &Expr) -> Expr {
+        match (self, other) {
+            (Expr::Linear(a), Expr::Linear(b)) => {
+                let product = if let Some(c) = b.as_constant() {
+                    a.scale(c)
+                } else if let Some(c) = a.as_constant() {
+                    b.scale(c)
+                } else {
+                    None
+                };
+                match product {
+                    Some(product) => Expr::Linear(product),
+                    None => Expr::Opaque(format!("({} * {})", self, other)),
+                }
+            }
+            _ => Expr::Opaque(format!("({} * {})", self, other)),
+        }
+    }
+
+    fn compare(&self, other: &Expr, operator: &str, result: bool) -> Expr {
+        match (self.as_constant(), other.as_constant()) {
+            (Some(_), Some(_)) => Expr::constant(if result { 1 } else { 0 }),
+            _ => Expr::Opaque(format!("({} {} {})", self, operator, other)),
+        }
+    }
+}
+
+impl fmt::Display for Linear {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for (name, c) in &self.coefficients {
+            let sign = if *c < 0 {
+                if first {
+                    "-"
+                } else {
+                    " - "
+                }
+            } else if first {
+                ""
+            } else {
+                " + "
+            };
+            if c.abs() == 1 {
+                write!(f, "{}{}", sign, name)?;
+            } else {
+                write!(f, "{}{}*{}", sign, c.abs(), name)?;
+            }
+            first = false;
+        }
+        if first {
+            return write!(f, "{}", self.constant);
+        }
+        if self.constant < 0 {
+            write!(f, " - {}", -self.constant)?;
+        } else if self.constant > 0 {
+            write!(f, " + {}", self.constant)?;
+        }
+        return Ok(());
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Linear(linear) => write!(f, "{}", linear),
+            Expr::Opaque(description) => write!(f, "{}", description),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::SymbolicBranch(p) => write!(f, "branch at {} depends on a variable", p),
+            Error::SymbolicAddress(p) => write!(f, "address at {} depends on a variable", p),
+            Error::InvalidAddress(p) => write!(f, "invalid address used at {}", p),
+            Error::InvalidInstruction(p) => write!(f, "invalid instruction at {}", p),
+            Error::Overflow(p) => write!(f, "address overflow at {}", p),
+            Error::StepLimit => write!(f, "step limit reached"),
+        }
+    }
+}
+
+impl Machine {
+    /// Replaces the cell at `address` with an unknown named `name`.
+    pub fn set_variable(&mut self, address: usize, name: &str) {
+        self.memory[address] = Expr::variable(name);
+    }
+
+    pub fn get_memory_at(&self, address: usize) -> &Expr {
+        return &self.memory[address];
+    }
+
+    /// Runs the program until it halts. Each read introduces a new variable
+    /// named `input_N` and outputs are discarded. The run stops with an error
+    /// as soon as control flow or a write address depends on a variable.
+    pub fn execute(&mut self) -> Result<(), Error> {
+        for _ in 0..MAX_STEPS {
+            let at = self.pointer;
+            let instruction = self.cell(at)?;
+            let (opcode, am, bm, cm) = parse_instruction(
+                instruction
+                    .as_constant()
+                    .ok_or(Error::SymbolicAddress(at))?,
+            );
+            match opcode {
+                INSTRUCTION_ADD | INSTRUCTION_MUL | INSTRUCTION_LESS_THAN | INSTRUCTION_EQUALS => {
+                    let a = self.get_value(am, 1)?;
+                    let b = self.get_value(bm, 2)?;
+                    let v = match opcode {
+                        INSTRUCTION_ADD => a.add(&b),
+                        INSTRUCTION_MUL => a.mul(&b),
+                        INSTRUCTION_LESS_THAN => {
+                            a.compare(&b, "<", a.as_constant() < b.as_constant())
+                        }
+                        _ => a.compare(&b, "==", a.as_constant() == b.as_constant()),
+                    };
+                    self.set_value(cm, 3, v)?;
+                    self.pointer += 4;
+                }
+                INSTRUCTION_READ => {
+                    let v = Expr::variable(&format!("input_{}", self.inputs));
+                    self.inputs += 1;
+                    self.set_value(am, 1, v)?;
+                    self.pointer += 2;
+                }
+                INSTRUCTION_WRITE => {
+                    self.get_value(am, 1)?;
+                    self.pointer += 2;
+                }
+                INSTRUCTION_JUMP_IF_TRUE | INSTRUCTION_JUMP_IF_FALSE => {
+                    let a = self.get_value(am, 1)?;
+                    let condition = a.as_constant().ok_or(Error::SymbolicBranch(at))?;
+                    if (condition != 0) == (opcode == INSTRUCTION_JUMP_IF_TRUE) {
+                        let b = self.get_value(bm, 2)?;
+                        self.pointer = self.address(b.as_constant())?;
+                    } else {
+                        self.pointer += 3;
+                    }
+                }
+                INSTRUCTION_RELATIVE_ADD => {
+                    let a = self.get_value(am, 1)?;
+                    let offset = a.as_constant().ok_or(Error::SymbolicAddress(at))?;
+                    self.relative_base = self
+                        .relative_base
+                        .checked_add(offset)
+                        .ok_or(Error::Overflow(at))?;
+                    self.pointer += 2;
+                }
+                INSTRUCTION_EXIT => return Ok(()),
+                _ => return Err(Error::InvalidInstruction(at)),
+            }
+        }
+        return Err(Error::StepLimit);
+    }
+
+    fn cell(&self, address: usize) -> Result<Expr, Error> {
+        return self
+            .memory
+            .get(address)
+            .cloned()
+            .ok_or(Error::InvalidAddress(self.pointer));
+    }
+
+    fn address(&self, value: Option<i64>) -> Result<usize, Error> {
+        let value = value.ok_or(Error::SymbolicAddress(self.pointer))?;
+        if value < 0 || self.memory.len() as i64 <= value {
+            return Err(Error::InvalidAddress(self.pointer));
+        }
+        return Ok(value as usize);
+    }
+
+    fn get_value(&self, mode: i64, offset: usize) -> Result<Expr, Error> {
+        let parameter = self.cell(self.pointer + offset)?;
+        match mode {
+            1 => Ok(parameter),
+            0 | 2 => {
+                let base = if mode == 2 { self.relative_base } else { 0 };
+                match parameter.as_constant() {
+                    Some(p) => {
+                        let address = base.checked_add(p).ok_or(Error::Overflow(self.pointer))?;
+                        self.cell(self.address(Some(address))?)
+                    }
+                    // The loaded value is unknown, but the run may go on as
+                    // long as nothing depends on it.
+                    None => Ok(Expr::Opaque(format!("mem[{}]", parameter))),
+                }
+            }
+            _ => Err(Error::InvalidInstruction(self.pointer)),
+        }
+    }
+
+    fn set_value(&mut self, mode: i64, offset: usize, v: Expr) -> Result<(), Error> {
+        let parameter = self.cell(self.pointer + offset)?;
+        let base = match mode {
+            0 => 0,
+            2 => self.relative_base,
+            _ => return Err(Error::InvalidInstruction(self.pointer)),
+        };
+        let p = parameter
+            .as_constant()
+            .ok_or(Error::SymbolicAddress(self.pointer))?;
+        let address = base.checked_add(p).ok_or(Error::Overflow(self.pointer))?;
+        let address = self.address(Some(address))?;
+        self.memory[address] = v;
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer;
+    use crate::computer::{Computer, Level};
+
+    #[test]
+    fn test_linear_arithmetic() {
+        // mem[0] = (x + 3) * 2, mem[9] = x
+        let mut machine = new(&[1001, 9, 3, 0, 1002, 0, 2, 0, 99, 0]);
+        machine.set_variable(9, "x");
+        assert_eq!(machine.execute(), Ok(()));
+        assert_eq!(format!("{}", machine.get_memory_at(0)), "2*x + 6");
+    }
+
+    #[test]
+    fn test_non_linear() {
+        let mut machine = new(&[2, 5, 6, 0, 99, 0, 0]);
+        machine.set_variable(5, "x");
+        machine.set_variable(6, "y");
+        assert_eq!(machine.execute(), Ok(()));
+        assert_eq!(format!("{}", machine.get_memory_at(0)), "(x * y)");
+    }
+
+    #[test]
+    fn test_symbolic_branch() {
+        let mut machine = new(&[1005, 4, 0, 99, 0]);
+        machine.set_variable(4, "x");
+        assert_eq!(machine.execute(), Err(Error::SymbolicBranch(0)));
+    }
+
+    #[test]
+    fn test_inputs() {
+        let mut machine = new(&[3, 0, 102, 3, 0, 0, 99]);
+        assert_eq!(machine.execute(), Ok(()));
+        assert_eq!(format!("{}", machine.get_memory_at(0)), "3*input_0");
+    }
+
+    #[test]
+    fn test_solve() {
+        let mut machine = new(&[1, 9, 10, 0, 1002, 0, 7, 0, 99, 0, 0]);
+        machine.set_variable(9, "a");
+        machine.set_variable(10, "b");
+        assert_eq!(machine.execute(), Ok(()));
+        let linear = match machine.get_memory_at(0) {
+            Expr::Linear(linear) => linear.clone(),
+            _ => panic!("Expected a linear expression"),
+        };
+        assert_eq!(
+            linear.solve(7 * 15, &[("a", 0, 9), ("b", 0, 9)]),
+            Some(vec![6, 9])
+        );
+        assert_eq!(linear.solve(7 * 15 + 1, &[("a", 0, 9), ("b", 0, 9)]), None);
+    }
+
+    #[test]
+    fn test_overflow() {
+        // mem[0] = x * MAX + MAX does not fit a linear expression.
+        let mut machine = new(&[1002, 9, i64::MAX, 0, 1001, 0, i64::MAX, 0, 99, 0]);
+        machine.set_variable(9, "x");
+        assert_eq!(machine.execute(), Ok(()));
+        assert_eq!(machine.get_memory_at(0).as_constant(), None);
+        let mut machine = new(&[1002, 9, 2, 0, 1001, 0, i64::MAX, 0, 99, 0]);
+        machine.set_variable(9, "x");
+        assert_eq!(machine.execute(), Ok(()));
+        match machine.get_memory_at(0) {
+            Expr::Linear(linear) => assert_eq!(linear.solve(i64::MIN, &[("x", 0, 9)]), None),
+            other => panic!("Expected a linear expression: {}", other),
+        }
+
+        let mut machine = new(&[109, i64::MAX, 109, 1, 99]);
+        assert_eq!(machine.execute(), Err(Error::Overflow(2)));
+        let mut machine = new(&[109, i64::MAX, 1201, 1, 0, 0, 99]);
+        assert_eq!(machine.execute(), Err(Error::Overflow(2)));
+    }
+
+    #[test]
+    fn test_matches_computer() {
+        let program = computer::load_program("inputs/day_2.txt");
+        let mut machine = new(&program);
+        machine.set_variable(1, "noun");
+        machine.set_variable(2, "verb");
+        assert_eq!(machine.execute(), Ok(()));
+        let linear = match machine.get_memory_at(0) {
+            Expr::Linear(linear) => linear.clone(),
+            _ => panic!("Expected a linear expression"),
+        };
+
+        for (noun, verb) in &[(0, 0), (12, 2), (64, 72), (99, 99)] {
+            let mut memory = program.clone();
+            memory[1] = *noun;
+            memory[2] = *verb;
+            let mut c = computer::new(Level::Day2, memory.len(), &memory);
+            assert_eq!(0, c.execute());
+            let expected = c.get_memory_at(0);
+            assert_eq!(
+                linear.constant
+                    + linear.coefficients["noun"] * noun
+                    + linear.coefficients["verb"] * verb,
+                expected
+            );
+        }
+    }
+}
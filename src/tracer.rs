@@ -0,0 +1,45 @@
+use crate::dialect::Dialect;
+use crate::disassembler::decode_with;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub trait Tracer {
+    /// Called before each instruction. The dialect lists the custom opcodes
+    /// the machine understands.
+    fn trace(&mut self, pointer: usize, relative_base: i64, memory: &[i64], dialect: &Dialect);
+}
+
+fn describe(pointer: usize, relative_base: i64, memory: &[i64], dialect: &Dialect) -> String {
+    match decode_with(dialect, memory, pointer) {
+        Some(instruction) => format!("{:>6} rb={:<6} {}", pointer, relative_base, instruction),
+        None => format!(
+            "{:>6} rb={:<6} <invalid {}>",
+            pointer, relative_base, memory[pointer]
+        ),
+    }
+}
+
+pub struct StdErr {}
+
+impl Tracer for StdErr {
+    fn trace(&mut self, pointer: usize, relative_base: i64, memory: &[i64], dialect: &Dialect) {
+        eprintln!("{}", describe(pointer, relative_base, memory, dialect));
+    }
+}
+
+/// Keeps every executed instruction, disassembled, in a shared log.
+pub struct Recorder {
+    log: Rc<RefCell<Vec<String>>>,
+}
+
+impl Tracer for Recorder {
+    fn trace(&mut self, pointer: usize, relative_base: i64, memory: &[i64], dialect: &Dialect) {
+        self.log
+            .borrow_mut()
+            .push(describe(pointer, relative_base, memory, dialect));
+    }
+}
+
+pub fn recorder(log: Rc<RefCell<Vec<String>>>) -> Recorder {
+    return Recorder { log: log };
+}
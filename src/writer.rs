@@ -1,3 +1,11 @@
+use crate::grid;
+use crate::grid::Sparse;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::mpsc::Sender;
 
 pub trait Writer {
@@ -10,9 +18,7 @@ impl Writer for StdOut {
     fn write(&self, value: i64) {
         use std::io::{stdout, Write};
         println!("Log: {}", value);
-        match stdout().flush() {
-            _ => {}
-        };
+        let _ = stdout().flush();
     }
 }
 
@@ -29,3 +35,196 @@ impl Writer for Channel {
 pub fn channel(sender: Sender<i64>) -> Channel {
     return Channel { sender: sender };
 }
+
+/// Appends outputs to a buffer shared with the host.
+pub struct Buffer {
+    values: Rc<RefCell<Vec<i64>>>,
+}
+
+impl Writer for Buffer {
+    fn write(&self, value: i64) {
+        self.values.borrow_mut().push(value);
+    }
+}
+
+pub fn buffer(values: Rc<RefCell<Vec<i64>>>) -> Buffer {
+    return Buffer { values: values };
+}
+
+struct Display {
+    tiles: Sparse<i64>,
+    score: Option<i64>,
+    glyphs: HashMap<i64, char>,
+    pending: Vec<i64>,
+    frames: Vec<String>,
+}
+
+/// Collects `(x, y, tile)` triples into a grid, except `(-1, 0, score)` which
+/// sets the score. Clones share the same grid, so keep one to inspect what the
+/// machine drew after registering another.
+#[derive(Clone)]
+pub struct Screen {
+    display: Rc<RefCell<Display>>,
+}
+
+impl Writer for Screen {
+    fn write(&self, value: i64) {
+        let mut display = self.display.borrow_mut();
+        display.pending.push(value);
+        if display.pending.len() < 3 {
+            return;
+        }
+        let triple: Vec<i64> = display.pending.drain(..).collect();
+        match (triple[0], triple[1], triple[2]) {
+            (-1, 0, score) => display.score = Some(score),
+            (x, y, tile) => {
+                display.tiles.insert(grid::point(x, y), tile);
+            }
+        }
+    }
+}
+
+/// A screen with the arcade glyphs: empty, wall, block, paddle and ball.
+pub fn screen() -> Screen {
+    let glyphs = [(0, ' '), (1, '#'), (2, '*'), (3, '-'), (4, 'o')];
+    return Screen {
+        display: Rc::new(RefCell::new(Display {
+            tiles: grid::sparse(),
+            score: None,
+            glyphs: glyphs.iter().cloned().collect(),
+            pending: Vec::new(),
+            frames: Vec::new(),
+        })),
+    };
+}
+
+impl Screen {
+    pub fn set_glyph(&self, tile: i64, glyph: char) {
+        self.display.borrow_mut().glyphs.insert(tile, glyph);
+    }
+
+    /// Tile at the position, 0 where nothing was drawn.
+    pub fn get_tile(&self, x: i64, y: i64) -> i64 {
+        return *self
+            .display
+            .borrow()
+            .tiles
+            .get(grid::point(x, y))
+            .unwrap_or(&0);
+    }
+
+    pub fn get_score(&self) -> Option<i64> {
+        return self.display.borrow().score;
+    }
+
+    pub fn count(&self, tile: i64) -> usize {
+        return self
+            .display
+            .borrow()
+            .tiles
+            .cells
+            .values()
+            .filter(|t| **t == tile)
+            .count();
+    }
+
+    /// The smallest rectangle holding every tile drawn so far, one line per
+    /// row, followed by the score if one was set. Tiles without a glyph show
+    /// as `?`.
+    pub fn render(&self) -> String {
+        let display = self.display.borrow();
+        let mut text = display
+            .tiles
+            .render(|tile| *display.glyphs.get(tile.unwrap_or(&0)).unwrap_or(&'?'));
+        if let Some(score) = display.score {
+            text += &format!("Score: {}\n", score);
+        }
+        return text;
+    }
+
+    /// Clears the terminal and draws the screen.
+    pub fn draw(&self) {
+        use std::io::{stdout, Write};
+        print!("\x1b[2J\x1b[H{}", self.render());
+        let _ = stdout().flush();
+    }
+
+    /// Records the current rendering as the next frame.
+    pub fn capture(&self) {
+        let frame = self.render();
+        self.display.borrow_mut().frames.push(frame);
+    }
+
+    pub fn get_frames(&self) -> Vec<String> {
+        return self.display.borrow().frames.clone();
+    }
+
+    /// Writes every captured frame to `frame_NNNNN.txt` in the directory.
+    pub fn export_frames(&self, directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+        for (i, frame) in self.display.borrow().frames.iter().enumerate() {
+            fs::write(directory.join(format!("frame_{:05}.txt", i)), frame)?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer;
+    use crate::computer::{Computer, Level};
+    use std::env;
+
+    fn draw(screen: &Screen, values: &[i64]) {
+        for value in values {
+            screen.write(*value);
+        }
+    }
+
+    #[test]
+    fn test_screen_from_program() {
+        let screen = screen();
+        // out 1, 2, 3, 6, 5, 4, -1, 0, 12345; hlt
+        let mut program = vec![];
+        for value in &[1, 2, 3, 6, 5, 4, -1, 0, 12345] {
+            program.extend(&[104, *value]);
+        }
+        program.push(99);
+        let mut c = computer::new(Level::Day9, program.len(), &program);
+        c.register_writer(Box::new(screen.clone()));
+        assert_eq!(c.execute(), 0);
+
+        assert_eq!(screen.get_tile(1, 2), 3);
+        assert_eq!(screen.get_tile(6, 5), 4);
+        assert_eq!(screen.get_tile(0, 0), 0);
+        assert_eq!(screen.get_score(), Some(12345));
+        assert_eq!(screen.count(3), 1);
+    }
+
+    #[test]
+    fn test_render() {
+        let screen = screen();
+        draw(&screen, &[0, 0, 1, 1, 0, 1, 2, 0, 1, 1, 1, 4, 2, 1, 9]);
+        assert_eq!(screen.render(), "###\n o?\n");
+        screen.set_glyph(9, '@');
+        draw(&screen, &[-1, 0, 7]);
+        assert_eq!(screen.render(), "###\n o@\nScore: 7\n");
+    }
+
+    #[test]
+    fn test_export_frames() {
+        let screen = screen();
+        draw(&screen, &[0, 0, 2]);
+        screen.capture();
+        draw(&screen, &[0, 0, 0, -1, 0, 1]);
+        screen.capture();
+        assert_eq!(screen.get_frames(), vec!["*\n", " \nScore: 1\n"]);
+
+        let directory = env::temp_dir().join(format!("screen-{}", std::process::id()));
+        screen.export_frames(&directory).unwrap();
+        let second = fs::read_to_string(directory.join("frame_00001.txt")).unwrap();
+        assert_eq!(second, " \nScore: 1\n");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
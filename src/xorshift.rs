@@ -0,0 +1,26 @@
+/// Small xorshift generator, so randomness needs no dependencies.
+pub struct Random {
+    state: u64,
+}
+
+pub fn random(seed: u64) -> Random {
+    return Random { state: seed.max(1) };
+}
+
+impl Random {
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
+    }
+
+    pub fn below(&mut self, n: u64) -> i64 {
+        return (self.next_u64() % n) as i64;
+    }
+
+    /// The state, which seeds a generator continuing the same sequence.
+    pub fn state(&self) -> u64 {
+        return self.state;
+    }
+}